@@ -20,6 +20,8 @@ use std::fs::OpenOptions;
 use std::path::Path;
 use std::{io, mem};
 
+#[cfg(unix)]
+use memmap2::Advice;
 use memmap2::Mmap;
 use solana_accounts_db::account_storage::meta::{AccountMeta, StoredMeta};
 use solana_accounts_db::accounts_file::ALIGN_BOUNDARY_OFFSET;
@@ -29,6 +31,8 @@ use solana_sdk::account::Account;
 use solana_sdk::hash::Hash;
 use tracing::info;
 
+use crate::error::AppendVecError;
+
 /// References to account data stored elsewhere. Getting an `Account` requires
 /// cloning (see `StoredAccountMeta::clone_account()`).
 #[derive(PartialEq, Eq, Debug)]
@@ -54,6 +58,34 @@ impl StoredAccountMeta<'_> {
             data: self.data.to_vec(),
         }
     }
+
+    /// Recomputes the account's hash (as the runtime does) & compares it to
+    /// the stored hash. Accounts stored without a hash (newer validators write
+    /// a zeroed hash) always verify.
+    pub(crate) fn verify_hash(&self) -> bool {
+        if self.hash == &Hash::default() {
+            return true;
+        }
+
+        &self.compute_hash() == self.hash
+    }
+
+    fn compute_hash(&self) -> Hash {
+        // NB: Zero lamport accounts are deleted & hash to the default hash.
+        if self.account_meta.lamports == 0 {
+            return Hash::default();
+        }
+
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.account_meta.lamports.to_le_bytes());
+        hasher.update(&self.account_meta.rent_epoch.to_le_bytes());
+        hasher.update(self.data);
+        hasher.update(&[self.account_meta.executable as u8]);
+        hasher.update(self.account_meta.owner.as_ref());
+        hasher.update(self.meta.pubkey.as_ref());
+
+        Hash::new_from_array(hasher.finalize().into())
+    }
 }
 
 /// A thread-safe, file-backed block of memory used to store `Account`
@@ -90,7 +122,7 @@ impl AppendVec {
             ))
         } else if current_len > file_size {
             Err(std::io::Error::new(
-                std::io::ErrorKind::Other,
+                std::io::ErrorKind::UnexpectedEof,
                 format!("current_len is larger than file size ({})", file_size),
             ))
         } else {
@@ -136,19 +168,30 @@ impl AppendVec {
         Ok(new)
     }
 
+    /// Advises the kernel on how the mapping will be accessed (e.g.
+    /// [`Advice::Sequential`] for full scans, [`Advice::Random`] for point
+    /// lookups).
+    #[cfg(unix)]
+    pub(crate) fn advise(&self, advice: Advice) -> io::Result<()> {
+        self.map.advise(advice)
+    }
+
     /// Get a reference to the data at `offset` of `size` bytes if that slice
-    /// doesn't overrun the internal buffer. Otherwise return None.
+    /// doesn't overrun the internal buffer. Otherwise return an error.
     /// Also return the offset of the first byte after the requested data that
     /// falls on a 64-byte boundary.
-    fn get_slice(&self, offset: usize, size: usize) -> Option<(&[u8], usize)> {
+    fn get_slice(&self, offset: usize, size: usize) -> Result<(&[u8], usize), AppendVecError> {
         let (next, overflow) = offset.overflowing_add(size);
-        if overflow || next > self.len() {
-            return None;
+        if overflow {
+            return Err(AppendVecError::Overflow { offset, size });
+        }
+        if next > self.len() {
+            return Err(AppendVecError::OutOfBounds { offset, size, len: self.len() });
         }
         let data = &self.map[offset..next];
         let next = u64_align!(next);
 
-        Some((
+        Ok((
             //UNSAFE: This unsafe creates a slice that represents a chunk of self.map memory
             //The lifetime of this slice is tied to &self, since it points to self.map memory
             unsafe { std::slice::from_raw_parts(data.as_ptr(), size) },
@@ -157,32 +200,50 @@ impl AppendVec {
     }
 
     /// Return a reference to the type at `offset` if its data doesn't overrun
-    /// the internal buffer. Otherwise return None. Also return the offset
+    /// the internal buffer. Otherwise return an error. Also return the offset
     /// of the first byte after the requested data that falls on a 64-byte
     /// boundary.
-    fn get_type<'a, T>(&self, offset: usize) -> Option<(&'a T, usize)> {
+    fn get_type<'a, T>(&self, offset: usize) -> Result<(&'a T, usize), AppendVecError> {
         let (data, next) = self.get_slice(offset, mem::size_of::<T>())?;
         let ptr: *const T = data.as_ptr() as *const T;
         //UNSAFE: The cast is safe because the slice is aligned and fits into the
         // memory and the lifetime of the &T is tied to self, which holds the
         // underlying memory map
-        Some((unsafe { &*ptr }, next))
+        Ok((unsafe { &*ptr }, next))
     }
 
     /// Return account metadata for the account at `offset` if its data doesn't
     /// overrun the internal buffer. Otherwise return None. Also return the
     /// offset of the first byte after the requested data that falls on a
     /// 64-byte boundary.
+    ///
+    /// See [`AppendVec::try_get_account`] to distinguish the end of the
+    /// accounts from a corrupt account.
     pub(crate) fn get_account<'a>(
         &'a self,
         offset: usize,
     ) -> Option<(StoredAccountMeta<'a>, usize)> {
+        self.try_get_account(offset).ok().flatten()
+    }
+
+    /// Return account metadata for the account at `offset`, or `None` if
+    /// `offset` is at (or past) the end of the accounts. Errors if the account
+    /// overruns the end of the accounts. Also return the offset of the first
+    /// byte after the requested data that falls on a 64-byte boundary.
+    pub(crate) fn try_get_account<'a>(
+        &'a self,
+        offset: usize,
+    ) -> Result<Option<(StoredAccountMeta<'a>, usize)>, AppendVecError> {
+        if offset >= self.len() {
+            return Ok(None);
+        }
+
         let (meta, next): (&'a StoredMeta, _) = self.get_type(offset)?;
         let (account_meta, next): (&'a AccountMeta, _) = self.get_type(next)?;
         let (hash, next): (&'a Hash, _) = self.get_type(next)?;
         let (data, next) = self.get_slice(next, meta.data_len as usize)?;
         let stored_size = next - offset;
-        Some((StoredAccountMeta { meta, account_meta, data, offset, stored_size, hash }, next))
+        Ok(Some((StoredAccountMeta { meta, account_meta, data, offset, stored_size, hash }, next)))
     }
 
     pub(crate) const fn slot(&self) -> u64 {
@@ -1,13 +1,470 @@
+use std::io::IsTerminal;
+use std::net::SocketAddr;
 use std::path::PathBuf;
 
-use clap::Parser;
+use clap::error::ErrorKind;
+use clap::{ArgAction, CommandFactory, Parser, Subcommand, ValueEnum};
+use indicatif::ProgressStyle;
+use solana_account_decoder::UiAccountEncoding;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::filter::AccountFilter;
+use crate::utils::{DEFAULT_MANIFEST_PROGRESS_TEMPLATE, DEFAULT_PROGRESS_TEMPLATE};
 
 #[derive(Debug, Parser)]
 #[clap(author, version, about)]
 pub(crate) struct Args {
-    /// Snapshot source (unpacked snapshot).
-    pub(crate) source: PathBuf,
+    #[clap(flatten)]
+    pub(crate) source: SourceArgs,
+    #[clap(flatten)]
+    pub(crate) index: IndexArgs,
+    #[clap(flatten)]
+    pub(crate) progress: ProgressArgs,
+    #[clap(subcommand)]
+    pub(crate) action: Action,
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct SourceArgs {
+    /// Snapshot source (unpacked snapshot), required unless
+    /// `--source-list-file` is set (or listing the RPC methods).
+    #[clap(long = "source")]
+    pub(crate) path: Option<PathBuf>,
+    /// File listing one snapshot source per line, processed in sequence
+    /// (supported by `info`, `checksum`, `export` & `check`).
+    #[clap(long, value_name = "PATH", conflicts_with = "path")]
+    pub(crate) source_list_file: Option<PathBuf>,
+    /// Incremental snapshot (unpacked) to layer over `--source`, which must
+    /// be the full snapshot it was taken against.
+    #[clap(long, alias = "incremental-snapshot", conflicts_with = "source_list_file")]
+    pub(crate) incremental_source: Option<PathBuf>,
+    /// Read append vecs that are symlinks (skipped by default).
+    #[clap(long)]
+    pub(crate) follow_symlinks: bool,
+    /// Behavior when an append vec cannot be opened.
+    #[clap(long, alias = "on-error", value_enum, default_value_t)]
+    pub(crate) on_file_error: OnError,
+    /// Behavior when an account in an append vec cannot be parsed.
+    #[clap(long, value_enum, default_value_t)]
+    pub(crate) on_parse_error: OnParseError,
+    /// Retry opening an append vec that is missing or shorter than the
+    /// manifest expects this many times (with exponential backoff), for
+    /// snapshot directories that are still being written.
+    #[clap(long, default_value_t = 0)]
+    pub(crate) open_retries: u32,
+    /// Reject manifests with bytes remaining after the known trailing fields
+    /// (e.g. fields appended by a newer snapshot version).
+    #[clap(long)]
+    pub(crate) strict_manifest: bool,
+    /// Refuse to process the snapshot unless its slot matches.
+    #[clap(long)]
+    pub(crate) require_slot: Option<u64>,
+}
+
+impl SourceArgs {
+    /// Exits with a usage error unless a source is set.
+    ///
+    /// NB: Not enforced by clap, as `rpc --list-methods` needs no source & clap
+    /// cannot condition a top level argument on a subcommand's.
+    pub(crate) fn require_source(&self) {
+        if self.path.is_none() && self.source_list_file.is_none() {
+            Args::command()
+                .error(
+                    ErrorKind::MissingRequiredArgument,
+                    "one of --source or --source-list-file is required",
+                )
+                .exit();
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OnError {
+    /// Abort the scan.
+    #[default]
+    Abort,
+    /// Log the append vec & continue the scan without it.
+    Skip,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub(crate) enum OnParseError {
+    /// Abort the scan.
+    Abort,
+    /// Log the append vec & continue the scan without any of its accounts
+    /// (append vecs are validated before their accounts are yielded, which
+    /// reads their account headers twice).
+    Skip,
+    /// Log the append vec & keep the accounts parsed before the error.
+    #[default]
+    Truncate,
+}
+
+#[derive(Debug, Subcommand)]
+pub(crate) enum Action {
+    /// Serve an RPC based on the snapshot's account state.
+    Rpc(RpcArgs),
+    /// Print the snapshot's metadata (reads only the manifest).
+    Info {
+        /// Print the metadata as JSON.
+        #[clap(long)]
+        json: bool,
+    },
+    /// Interactively look up accounts by pubkey (reads commands from stdin).
+    Repl,
+    /// Print a deterministic digest of the newest version of every account.
+    Checksum,
+    /// Export the newest version of every account.
+    Export(ExportArgs),
+    /// Verify every append vec referenced by the manifest is present on disk.
+    Check,
+    /// Print the newest version of every pubkey listed in a file as JSON lines.
+    Lookup {
+        /// File containing one pubkey per line.
+        #[clap(long)]
+        pubkeys_file: PathBuf,
+    },
+    /// Print every stored account version as JSON lines (base64 data).
+    Dump {
+        /// Stop after this many accounts.
+        #[clap(long)]
+        limit: Option<usize>,
+    },
+    /// Print the raw layout of every account in a single append vec.
+    DumpAppendVec {
+        /// Append vec to dump.
+        #[clap(value_name = "SLOT.ID")]
+        append_vec: String,
+    },
+    /// Write the append vec count, account count & account data bytes of
+    /// every slot as CSV.
+    SlotHistogram {
+        /// Output path.
+        #[clap(long)]
+        out: PathBuf,
+    },
+    /// Print the pubkey, first match offset & slot of every stored account
+    /// version whose data contains a byte pattern (without building an index).
+    Grep(GrepArgs),
+    /// Print account totals, the owners with the most accounts & the largest
+    /// accounts (without building an index).
+    Stats {
+        /// Number of owners & largest accounts to print.
+        #[clap(long, default_value_t = 10)]
+        top: usize,
+        /// Print the stats as JSON.
+        #[clap(long)]
+        json: bool,
+        /// Count unique pubkeys exactly (memory grows with the number of
+        /// pubkeys) instead of estimating the count in constant memory.
+        #[clap(long)]
+        exact_unique: bool,
+    },
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct RpcArgs {
+    /// Address to serve the RPC on.
+    #[clap(long, default_value = "0.0.0.0:8899")]
+    pub(crate) listen_addr: SocketAddr,
     /// Requests to `getTransaction` will be forward to this RPC.
     #[clap(long)]
     pub(crate) transaction_rpc: Option<String>,
+    /// Response to `getTransaction` if no `--transaction-rpc` is configured.
+    #[clap(long, value_enum, default_value_t)]
+    pub(crate) missing_transaction_rpc: MissingTransactionRpc,
+    /// Respond to `getAccountInfo` with an internal error (rather than `null`)
+    /// if the account is indexed but its append vec could not be read.
+    #[clap(long)]
+    pub(crate) strict_lookups: bool,
+    /// Account encoding used when a request omits `encoding` (a request's
+    /// `encoding` always takes precedence).
+    #[clap(long, value_enum, default_value_t)]
+    pub(crate) default_encoding: DefaultEncoding,
+    /// Log this fraction of RPC calls (method, params, result size & latency),
+    /// failed calls are always logged when set.
+    #[clap(long, value_name = "RATE", value_parser = parse_sample_rate)]
+    pub(crate) rpc_log_sample: Option<f64>,
+    /// Report this slot in response contexts instead of the snapshot slot
+    /// (e.g. for hand-built fixtures with slot 0).
+    #[clap(long)]
+    pub(crate) context_slot: Option<u64>,
+    /// Keep HTTP connections alive between requests (disable for many
+    /// short-lived clients).
+    #[clap(long, default_value_t = true, action = ArgAction::Set)]
+    pub(crate) keep_alive: bool,
+    /// Maximum HTTP request body size in bytes.
+    #[clap(long, default_value_t = 5 * 1024 * 1024)]
+    pub(crate) max_request_body: usize,
+    /// Reject requests with a `dataSlice` length above this many bytes (zero
+    /// length slices are always served).
+    #[clap(long, default_value_t = 10 * 1024 * 1024)]
+    pub(crate) data_slice_max: usize,
+    /// Only serve these methods (repeatable, defaults to all methods).
+    #[clap(long, value_name = "METHOD")]
+    pub(crate) enable_method: Vec<String>,
+    /// Do not serve this method (repeatable, takes precedence over
+    /// `--enable-method`).
+    #[clap(long, value_name = "METHOD")]
+    pub(crate) disable_method: Vec<String>,
+    /// Time this many account lookups (of randomly sampled indexed keys) after
+    /// indexing & log the latency percentiles before serving.
+    #[clap(long, default_value_t = 0)]
+    pub(crate) warmup_queries: usize,
+    /// Seed of the `--warmup-queries` key sample.
+    #[clap(long, default_value_t = 0)]
+    pub(crate) warmup_seed: u64,
+    /// Print the supported RPC methods & exit.
+    #[clap(long)]
+    pub(crate) list_methods: bool,
+}
+
+fn parse_sample_rate(rate: &str) -> Result<f64, String> {
+    let rate: f64 = rate.parse().map_err(|err| format!("{err}"))?;
+    if rate.is_nan() || rate <= 0.0 || rate > 1.0 {
+        return Err("expected a rate in (0, 1]".to_string());
+    }
+
+    Ok(rate)
+}
+
+#[derive(Debug, Clone, clap::Args)]
+pub(crate) struct ExportArgs {
+    /// Output format.
+    #[clap(long, value_enum)]
+    pub(crate) format: ExportFormat,
+    /// Output path (a directory, or the file written by `--format csv` &
+    /// `--format parquet`). With `--source-list-file`, each snapshot is
+    /// written to `<out>/<slot>` (or `<out>/<slot>.csv`/`.parquet`).
+    #[clap(long)]
+    pub(crate) out: PathBuf,
+    /// Include each account's data (base64 encoded) in `--format csv` &
+    /// `--format parquet` exports.
+    #[clap(long)]
+    pub(crate) include_data: bool,
+    /// Rewrite the `owner` of exported accounts owned by `<FROM>` to `<TO>`
+    /// (repeatable). Only the exported owner field is changed, account data
+    /// is written unmodified.
+    #[clap(long, value_name = "FROM:TO", value_parser = parse_owner_rename)]
+    pub(crate) owner_rename: Vec<(Pubkey, Pubkey)>,
+    /// Export the `rent_epoch` of rent exempt accounts (`u64::MAX`) as `0`,
+    /// so diffs across snapshots ignore rent epoch churn.
+    #[clap(long)]
+    pub(crate) normalize_rent_epoch: bool,
+    /// Also export zero lamport accounts (accounts closed as of the snapshot
+    /// slot), which are skipped by default.
+    #[clap(long)]
+    pub(crate) emit_empty_accounts: bool,
+    /// Write buffer size in bytes (raise to MiBs for network file systems).
+    #[clap(long, default_value_t = 64 * 1024)]
+    pub(crate) output_buffer_size: usize,
+}
+
+fn parse_owner_rename(rename: &str) -> Result<(Pubkey, Pubkey), String> {
+    let (from, to) = rename.split_once(':').ok_or("expected <FROM>:<TO>")?;
+    let from = from
+        .parse()
+        .map_err(|err| format!("invalid <FROM> pubkey; err={err}"))?;
+    let to = to
+        .parse()
+        .map_err(|err| format!("invalid <TO> pubkey; err={err}"))?;
+
+    Ok((from, to))
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct GrepArgs {
+    /// Hex encoded byte pattern to search account data for.
+    #[clap(long, value_parser = parse_hex)]
+    pub(crate) pattern: Box<[u8]>,
+}
+
+fn parse_hex(hex: &str) -> Result<Box<[u8]>, String> {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+    if hex.is_empty() || hex.len() % 2 != 0 {
+        return Err("expected a non-empty, even number of hex digits".to_string());
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| {
+            hex.get(i..i + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| format!("invalid hex byte at offset {i}"))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub(crate) enum ExportFormat {
+    /// One `<pubkey>.bin` (data) & `<pubkey>.json` (metadata) per account,
+    /// sharded into subdirectories by pubkey prefix.
+    Files,
+    /// One `<pubkey>.json` per account, loadable with
+    /// `solana-test-validator --account-dir`.
+    TestValidator,
+    /// A single CSV file with one row of account metadata per account.
+    Csv,
+    /// A single Parquet file with the same columns as `csv`.
+    Parquet,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub(crate) enum MissingTransactionRpc {
+    /// Reject the request with an invalid params error.
+    #[default]
+    InvalidParams,
+    /// Reject the request with a method not found error.
+    MethodNotFound,
+    /// Respond with `null` (i.e. transaction not found).
+    Null,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub(crate) enum DefaultEncoding {
+    #[default]
+    Base64,
+    /// Accounts with more than 128 bytes of data are rejected.
+    Base58,
+    /// Falls back to base64 for accounts without a known parser.
+    #[value(name = "jsonParsed")]
+    JsonParsed,
+}
+
+impl From<DefaultEncoding> for UiAccountEncoding {
+    fn from(value: DefaultEncoding) -> Self {
+        match value {
+            DefaultEncoding::Base64 => UiAccountEncoding::Base64,
+            DefaultEncoding::Base58 => UiAccountEncoding::Base58,
+            DefaultEncoding::JsonParsed => UiAccountEncoding::JsonParsed,
+        }
+    }
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct IndexArgs {
+    #[clap(flatten)]
+    pub(crate) filter: AccountFilter,
+    /// Pack each index entry's `(slot, id)` into a single `u64` (a third of
+    /// the index value size, requires slots < 2^40 & ids < 2^24). Packed
+    /// entries do not store the account's offset, so lookups scan the append
+    /// vec.
+    #[clap(long)]
+    pub(crate) packed_index: bool,
+    /// Index token accounts by their token owner (required for
+    /// `getTokenAccountsByOwner`).
+    #[clap(long)]
+    pub(crate) token_owner_index: bool,
+    /// Index accounts by their owner program (required for
+    /// `getProgramAccounts`, costs ~32 bytes per indexed account).
+    #[clap(long)]
+    pub(crate) program_owner_index: bool,
+    /// Which version of each account to index.
+    #[clap(long, value_enum, default_value_t)]
+    pub(crate) index_policy: IndexPolicy,
+    /// Abort (rather than warn) if the bytes parsed from the append vecs do
+    /// not sum to the manifest's total account bytes.
+    #[clap(long)]
+    pub(crate) strict_scan: bool,
+    /// Recompute the hash of every scanned account & abort if any differ from
+    /// the stored hashes.
+    #[clap(long)]
+    pub(crate) verify_hashes: bool,
+    /// Size the account index for this many accounts (instead of estimating
+    /// the count from the manifest's account bytes).
+    #[clap(long)]
+    pub(crate) expected_accounts: Option<usize>,
+    /// Size the account index from the account count stored in this file (if
+    /// present, ignored under `--expected-accounts`), & store the final account
+    /// count in it once the index is built. Avoids rehashing when re-running
+    /// against the same snapshot.
+    #[clap(long, value_name = "PATH")]
+    pub(crate) account_capacity_hint: Option<PathBuf>,
+    /// Threads used to scan append vecs while building the index (0 for one
+    /// per CPU).
+    #[clap(long, default_value_t = 1)]
+    pub(crate) num_threads: usize,
+    /// Load the account index from a file written by `--save-index` instead
+    /// of scanning the snapshot (account filters are not re-applied).
+    #[clap(
+        long,
+        conflicts_with_all = ["token_owner_index", "program_owner_index", "verify_hashes"]
+    )]
+    pub(crate) load_index: Option<PathBuf>,
+    /// Write the account index to this file once built (records are sorted by
+    /// pubkey, so the file does not depend on `--num-threads`).
+    #[clap(long)]
+    pub(crate) save_index: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Default, ValueEnum)]
+pub(crate) enum IndexPolicy {
+    /// Index the version with the highest slot (the current state).
+    #[default]
+    Newest,
+    /// Index the version with the lowest slot (the first appearance).
+    Oldest,
+}
+
+#[derive(Debug, clap::Args)]
+pub(crate) struct ProgressArgs {
+    /// Disable progress bars (also disabled if stderr is not a terminal).
+    #[clap(long)]
+    pub(crate) quiet: bool,
+    /// Seconds between progress log lines when progress bars are disabled.
+    #[clap(long, default_value_t = 30)]
+    pub(crate) progress_interval: u64,
+    /// `indicatif` template of the index progress bars.
+    #[clap(long, value_name = "TEMPLATE", value_parser = parse_progress_template)]
+    pub(crate) progress_template: Option<String>,
+    /// `indicatif` template of the manifest read progress bar.
+    #[clap(long, value_name = "TEMPLATE", value_parser = parse_progress_template)]
+    pub(crate) manifest_progress_template: Option<String>,
+}
+
+impl ProgressArgs {
+    pub(crate) fn quiet(&self) -> bool {
+        self.quiet || !std::io::stderr().is_terminal()
+    }
+
+    pub(crate) fn style(&self) -> ProgressStyle {
+        progress_style(
+            self.progress_template
+                .as_deref()
+                .unwrap_or(DEFAULT_PROGRESS_TEMPLATE),
+        )
+    }
+
+    pub(crate) fn manifest_style(&self) -> ProgressStyle {
+        progress_style(
+            self.manifest_progress_template
+                .as_deref()
+                .unwrap_or(DEFAULT_MANIFEST_PROGRESS_TEMPLATE),
+        )
+        .progress_chars("#>-")
+    }
+}
+
+fn progress_style(template: &str) -> ProgressStyle {
+    // NB: Custom templates are validated by `parse_progress_template`.
+    ProgressStyle::with_template(template).unwrap()
+}
+
+fn parse_progress_template(template: &str) -> Result<String, String> {
+    ProgressStyle::with_template(template)
+        .map(|_| template.to_string())
+        .map_err(|err| format!("invalid progress template; err={err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn list_methods_without_source() {
+        let args = Args::try_parse_from(["solana-snapshot-rpc", "rpc", "--list-methods"]).unwrap();
+
+        assert!(args.source.path.is_none());
+        assert!(matches!(args.action, Action::Rpc(RpcArgs { list_methods: true, .. })));
+    }
 }
@@ -0,0 +1,114 @@
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use tracing::{error, info};
+
+use crate::args::{Action, Args, ExportArgs, ExportFormat};
+use crate::info::SnapshotInfo;
+use crate::unpacked::UnpackedSnapshotExtractor;
+use crate::utils::LoadProgressTracking;
+use crate::{checksum, export, load_index};
+
+/// Runs `args.action` against every snapshot source listed in
+/// `source_list_file` (one per line, blank lines & `#` comments are ignored),
+/// continuing past failed sources. Exits with an error if any source failed.
+pub(crate) fn run(args: &Args, source_list_file: &Path) {
+    assert!(
+        matches!(
+            args.action,
+            Action::Info { .. } | Action::Checksum | Action::Export(_) | Action::Check
+        ),
+        "--source-list-file only supports info, checksum, export & check"
+    );
+
+    let sources = std::fs::read_to_string(source_list_file).unwrap_or_else(|err| {
+        panic!("Failed to read source list; path={source_list_file:?}; err={err}")
+    });
+    let sources: Vec<_> = sources
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect();
+
+    let mut failed = Vec::new();
+    for source in &sources {
+        info!(source, "Processing snapshot");
+
+        // NB: Snapshot errors panic, so catch them to continue with the next source.
+        let result = std::panic::catch_unwind(AssertUnwindSafe(|| run_source(args, source)));
+        match result {
+            Ok(Ok(())) => {}
+            Ok(Err(err)) => {
+                error!(source, %err, "Snapshot failed");
+                failed.push(*source);
+            }
+            Err(_) => {
+                error!(source, "Snapshot failed (see panic above)");
+                failed.push(*source);
+            }
+        }
+    }
+
+    info!(sources = sources.len(), failed = failed.len(), ?failed, "Source list complete");
+    if !failed.is_empty() {
+        std::process::exit(1);
+    }
+}
+
+/// Runs `args.action` against a single source, tagging its output with the
+/// snapshot slot.
+fn run_source(args: &Args, source: &str) -> Result<(), String> {
+    let loader = UnpackedSnapshotExtractor::open(
+        Path::new(source),
+        &args.source,
+        Box::new(LoadProgressTracking {
+            quiet: args.progress.quiet(),
+            style: args.progress.manifest_style(),
+        }),
+    );
+    let slot = loader.slot();
+
+    match &args.action {
+        // NB: The info output already contains the slot.
+        Action::Info { json } => SnapshotInfo::new(&loader).print(*json),
+        Action::Checksum => {
+            let (digest, accounts) = checksum::checksum(&load_index(
+                loader,
+                &args.index,
+                &args.progress,
+                &AtomicBool::default(),
+            ));
+
+            println!("{slot} {digest} ({accounts} accounts)");
+        }
+        Action::Export(export_args) => {
+            let out = match export_args.format {
+                ExportFormat::Csv => export_args.out.join(format!("{slot}.csv")),
+                ExportFormat::Parquet => export_args.out.join(format!("{slot}.parquet")),
+                ExportFormat::Files | ExportFormat::TestValidator => {
+                    export_args.out.join(slot.to_string())
+                }
+            };
+            let export_args = ExportArgs { out, ..export_args.clone() };
+
+            export::export(
+                &load_index(loader, &args.index, &args.progress, &AtomicBool::default()),
+                &export_args,
+            );
+        }
+        Action::Check => {
+            let errors = loader.verify_append_vec_files();
+            for error in &errors {
+                println!("{slot} {error}");
+            }
+
+            if !errors.is_empty() {
+                return Err(format!("Found {} invalid append vec(s)", errors.len()));
+            }
+        }
+        _ => unreachable!(),
+    }
+
+    Ok(())
+}
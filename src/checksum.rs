@@ -0,0 +1,180 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, ErrorKind, Read, Seek, Write};
+
+use solana_sdk::hash::{hashv, Hash, Hasher};
+use solana_sdk::pubkey::Pubkey;
+use tracing::info;
+
+use crate::append_vec::StoredAccountMeta;
+use crate::rpc::HistoricalRpc;
+
+/// Number of `(pubkey, digest)` records (64 bytes each) sorted in memory before
+/// being spilled to a temporary chunk file (512 MiB).
+const SORT_CHUNK_RECORDS: usize = 8 * 1024 * 1024;
+
+/// Computes a deterministic digest over the newest version of every account in
+/// the snapshot.
+///
+/// Each account is encoded as the following record (integers are little
+/// endian):
+///
+/// | field        | encoding   |
+/// |--------------|------------|
+/// | `pubkey`     | 32 bytes   |
+/// | `owner`      | 32 bytes   |
+/// | `lamports`   | `u64`      |
+/// | `executable` | `u8` (0/1) |
+/// | `rent_epoch` | `u64`      |
+/// | `data_len`   | `u64`      |
+/// | `data`       | `data_len` |
+///
+/// The final digest is the SHA-256 of the concatenated SHA-256 record digests,
+/// ordered by pubkey. Record digests are sorted externally: sorted chunks of
+/// [`SORT_CHUNK_RECORDS`] are spilled to temporary files & then merged.
+pub(crate) fn checksum(rpc: &HistoricalRpc) -> (Hash, usize) {
+    let digests = rpc.newest_accounts().map(|(_, _, account)| {
+        let account = account.access().unwrap();
+
+        (account.meta.pubkey, record_digest(&account))
+    });
+
+    fold_sorted(digests, SORT_CHUNK_RECORDS)
+}
+
+/// Folds the digests ordered by pubkey (keeping the first occurrence of each
+/// pubkey), sorting at most `chunk_records` in memory at once.
+fn fold_sorted(
+    digests: impl Iterator<Item = (Pubkey, Hash)>,
+    chunk_records: usize,
+) -> (Hash, usize) {
+    let mut chunks = Vec::new();
+    let mut chunk = Vec::new();
+    for record in digests {
+        chunk.push(record);
+        if chunk.len() == chunk_records {
+            chunks.push(spill_chunk(&mut chunk));
+        }
+    }
+
+    let mut hasher = Hasher::default();
+    let mut accounts = 0;
+    let mut fold = |digest: &Hash| {
+        hasher.hash(digest.as_ref());
+        accounts += 1;
+    };
+    if chunks.is_empty() {
+        sort_chunk(&mut chunk);
+        chunk.iter().for_each(|(_, digest)| fold(digest));
+    } else {
+        if !chunk.is_empty() {
+            chunks.push(spill_chunk(&mut chunk));
+        }
+        info!(chunks = chunks.len(), "Merging sorted account digest chunks");
+        merge_chunks(chunks, fold);
+    }
+    info!(accounts, "Sorted account digests");
+
+    (hasher.result(), accounts)
+}
+
+fn sort_chunk(chunk: &mut Vec<(Pubkey, Hash)>) {
+    // NB: Stable sort so the first occurrence of a key wins, matching the index.
+    chunk.sort_by_key(|(key, _)| *key);
+    chunk.dedup_by_key(|(key, _)| *key);
+}
+
+/// Sorts & writes `chunk` to an anonymous temporary file, clearing it.
+fn spill_chunk(chunk: &mut Vec<(Pubkey, Hash)>) -> File {
+    sort_chunk(chunk);
+
+    let mut writer = BufWriter::new(tempfile::tempfile().unwrap());
+    for (key, digest) in chunk.drain(..) {
+        writer.write_all(key.as_ref()).unwrap();
+        writer.write_all(digest.as_ref()).unwrap();
+    }
+    let mut file = writer.into_inner().unwrap();
+    file.rewind().unwrap();
+
+    file
+}
+
+/// K-way merges the sorted chunk files, calling `fold` with each digest in
+/// pubkey order. Keys present in several chunks keep the earliest chunk's
+/// digest.
+fn merge_chunks(chunks: Vec<File>, mut fold: impl FnMut(&Hash)) {
+    let mut readers: Vec<_> = chunks.into_iter().map(BufReader::new).collect();
+    let mut heap = BinaryHeap::with_capacity(readers.len());
+    for (i, reader) in readers.iter_mut().enumerate() {
+        if let Some((key, digest)) = read_record(reader) {
+            heap.push(Reverse((key, i, digest)));
+        }
+    }
+
+    let mut previous = None;
+    while let Some(Reverse((key, i, digest))) = heap.pop() {
+        if previous != Some(key) {
+            fold(&digest);
+            previous = Some(key);
+        }
+        if let Some((key, digest)) = read_record(&mut readers[i]) {
+            heap.push(Reverse((key, i, digest)));
+        }
+    }
+}
+
+fn read_record(reader: &mut impl Read) -> Option<(Pubkey, Hash)> {
+    let mut record = [0; 64];
+    match reader.read_exact(&mut record) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return None,
+        Err(err) => panic!("Failed to read sorted digest chunk; err={err}"),
+    }
+    let (key, digest) = record.split_at(32);
+
+    Some((Pubkey::try_from(key).unwrap(), Hash::new_from_array(digest.try_into().unwrap())))
+}
+
+fn record_digest(account: &StoredAccountMeta) -> Hash {
+    hashv(&[
+        account.meta.pubkey.as_ref(),
+        account.account_meta.owner.as_ref(),
+        &account.account_meta.lamports.to_le_bytes(),
+        &[account.account_meta.executable as u8],
+        &account.account_meta.rent_epoch.to_le_bytes(),
+        &(account.data.len() as u64).to_le_bytes(),
+        account.data,
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_sort_matches_in_memory_sort() {
+        let keys: Vec<_> = (0..10).map(|_| Pubkey::new_unique()).collect();
+        // NB: Reversed & with a duplicate key spanning chunks, whose first digest wins.
+        let digests: Vec<_> = keys
+            .iter()
+            .rev()
+            .enumerate()
+            .map(|(i, key)| (*key, hashv(&[&[i as u8]])))
+            .chain([(keys[9], hashv(&[b"duplicate"]))])
+            .collect();
+
+        let (expected, accounts) = fold_sorted(digests.iter().copied(), usize::MAX);
+        assert_eq!(accounts, 10);
+        let mut hasher = Hasher::default();
+        for key in &keys {
+            let (_, digest) = digests.iter().find(|(k, _)| k == key).unwrap();
+            hasher.hash(digest.as_ref());
+        }
+        assert_eq!(expected, hasher.result());
+
+        for chunk_records in [1, 3, 10] {
+            assert_eq!(fold_sorted(digests.iter().copied(), chunk_records), (expected, 10));
+        }
+    }
+}
@@ -0,0 +1,90 @@
+use std::ffi::OsStr;
+use std::io::{BufWriter, ErrorKind, Write};
+
+use base64::prelude::{Engine, BASE64_STANDARD};
+use serde_json::json;
+
+use crate::filter::AccountFilter;
+use crate::unpacked::UnpackedSnapshotExtractor;
+use crate::utils::{accounts_with_location, parse_append_vec_name};
+
+/// Prints every stored account version (not only the newest) matching
+/// `filter` as a JSON line, stopping after `limit` accounts (if set).
+pub(crate) fn dump_accounts(
+    extractor: &UnpackedSnapshotExtractor,
+    filter: &AccountFilter,
+    limit: Option<usize>,
+) {
+    let mut stdout = BufWriter::new(std::io::stdout().lock());
+    let accounts = accounts_with_location(extractor)
+        .filter(|(_, _, account)| filter.matches(&account.access().unwrap()))
+        .take(limit.unwrap_or(usize::MAX));
+    for (slot, _, account) in accounts {
+        let account = account.access().unwrap();
+        let record = json!({
+            "pubkey": account.meta.pubkey.to_string(),
+            "owner": account.account_meta.owner.to_string(),
+            "lamports": account.account_meta.lamports,
+            "executable": account.account_meta.executable,
+            "rent_epoch": account.account_meta.rent_epoch,
+            "slot": slot,
+            "data": BASE64_STANDARD.encode(account.data),
+        });
+
+        match writeln!(stdout, "{record}") {
+            Ok(()) => {}
+            // NB: The reader exited (e.g. `| head`), so stop quietly.
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => return,
+            Err(err) => panic!("Failed to write dump; err={err}"),
+        }
+    }
+
+    let _ = stdout.flush();
+    extractor.log_scan_errors();
+}
+
+/// Prints the raw layout of every account in a single append vec & where
+/// iteration stopped relative to its `current_len`.
+pub(crate) fn dump_append_vec(extractor: &UnpackedSnapshotExtractor, name: &str) {
+    let (slot, id) = parse_append_vec_name(OsStr::new(name))
+        .unwrap_or_else(|| panic!("Invalid append vec name, expected SLOT.ID; name={name}"));
+    let path = extractor.append_vec_path(slot, id);
+    let append_vec = extractor
+        .open_append_vec(slot, id, &path)
+        .unwrap_or_else(|err| panic!("{err}"));
+
+    let mut offset = 0;
+    let mut accounts = 0;
+    let stop = loop {
+        match append_vec.try_get_account(offset) {
+            Ok(Some((account, next_offset))) => {
+                println!(
+                    "offset={offset} data_len={} stored_size={} pubkey={} owner={} lamports={} \
+                     hash={}",
+                    account.meta.data_len,
+                    account.stored_size,
+                    account.meta.pubkey,
+                    account.account_meta.owner,
+                    account.account_meta.lamports,
+                    account.hash,
+                );
+
+                offset = next_offset;
+                accounts += 1;
+            }
+            Ok(None) => break None,
+            Err(err) => break Some(err),
+        }
+    };
+
+    let current_len = append_vec.len();
+    match stop {
+        None => {
+            println!("Reached end; accounts={accounts}; offset={offset}; current_len={current_len}")
+        }
+        Some(err) => println!(
+            "Stopped early; accounts={accounts}; offset={offset}; current_len={current_len}; \
+             err={err}"
+        ),
+    }
+}
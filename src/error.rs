@@ -0,0 +1,155 @@
+use std::fmt::Display;
+use std::path::PathBuf;
+
+use solana_runtime::snapshot_utils::SnapshotVersion;
+use solana_sdk::pubkey::Pubkey;
+
+pub(crate) type SnapshotResult<T> = Result<T, SnapshotError>;
+
+#[derive(Debug)]
+pub(crate) enum SnapshotError {
+    /// The append vec is not referenced by the snapshot manifest.
+    UnknownAppendVec { slot: u64, id: u64 },
+    /// The append vec could not be opened (or failed validation).
+    AppendVecOpen { slot: u64, id: u64, err: std::io::Error },
+    /// The index references an append vec that does not contain the account.
+    AccountMissing { slot: u64, id: u64, key: Pubkey },
+    /// The snapshot's accounts directory could not be listed.
+    AccountsDirRead { path: PathBuf, err: std::io::Error },
+    /// The snapshot's version file could not be read.
+    VersionRead { path: PathBuf, err: std::io::Error },
+    /// The snapshot's version file names a format this crate cannot parse.
+    UnsupportedVersion { version: String },
+}
+
+impl SnapshotError {
+    /// The `(slot, id)` of the append vec that caused the error, if any.
+    pub(crate) const fn append_vec(&self) -> Option<(u64, u64)> {
+        match self {
+            SnapshotError::UnknownAppendVec { slot, id }
+            | SnapshotError::AppendVecOpen { slot, id, .. }
+            | SnapshotError::AccountMissing { slot, id, .. } => Some((*slot, *id)),
+            SnapshotError::AccountsDirRead { .. }
+            | SnapshotError::VersionRead { .. }
+            | SnapshotError::UnsupportedVersion { .. } => None,
+        }
+    }
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SnapshotError::UnknownAppendVec { slot, id } => {
+                write!(f, "Unknown append vec; slot={slot}; id={id}")
+            }
+            SnapshotError::AppendVecOpen { slot, id, err } => {
+                write!(f, "Failed to open append vec; slot={slot}; id={id}; err={err}")
+            }
+            SnapshotError::AccountMissing { slot, id, key } => {
+                write!(
+                    f,
+                    "Indexed account missing from append vec; slot={slot}; id={id}; key={key}"
+                )
+            }
+            SnapshotError::AccountsDirRead { path, err } => {
+                write!(f, "Failed to read accounts directory; path={path:?}; err={err}")
+            }
+            SnapshotError::VersionRead { path, err } => {
+                write!(f, "Failed to read snapshot version; path={path:?}; err={err}")
+            }
+            SnapshotError::UnsupportedVersion { version } => {
+                write!(
+                    f,
+                    "Unsupported snapshot version; version={version:?}; supported={:?}",
+                    SnapshotVersion::default().as_str()
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for SnapshotError {}
+
+/// Errors loading a persisted [`crate::index::AccountIndex`].
+#[derive(Debug)]
+pub(crate) enum IndexFileError {
+    Io(std::io::Error),
+    /// The file does not start with the index magic bytes.
+    InvalidMagic([u8; 4]),
+    /// The file was written by an incompatible version of the format.
+    UnsupportedVersion(u16),
+    /// The index was built from a snapshot at a different slot.
+    SlotMismatch {
+        expected: u64,
+        found: u64,
+    },
+    /// The index scheme byte is not recognized.
+    UnknownScheme(u8),
+    /// The file length does not match the header's record count.
+    LengthMismatch {
+        count: u64,
+        records_len: u64,
+    },
+}
+
+impl From<std::io::Error> for IndexFileError {
+    fn from(err: std::io::Error) -> Self {
+        IndexFileError::Io(err)
+    }
+}
+
+impl Display for IndexFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            IndexFileError::Io(err) => write!(f, "I/O error; err={err}"),
+            IndexFileError::InvalidMagic(magic) => {
+                write!(f, "Not an index file; magic={magic:?}")
+            }
+            IndexFileError::UnsupportedVersion(version) => {
+                write!(f, "Unsupported index file version; version={version}")
+            }
+            IndexFileError::SlotMismatch { expected, found } => {
+                write!(
+                    f,
+                    "Index built for a different snapshot; expected={expected}; found={found}"
+                )
+            }
+            IndexFileError::UnknownScheme(scheme) => {
+                write!(f, "Unknown index scheme; scheme={scheme}")
+            }
+            IndexFileError::LengthMismatch { count, records_len } => {
+                write!(
+                    f,
+                    "Index file length does not match its record count; count={count}; \
+                     records_len={records_len}"
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for IndexFileError {}
+
+/// Errors reading an account from an [`crate::append_vec::AppendVec`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum AppendVecError {
+    /// `offset + size` overflows a `usize`.
+    Overflow { offset: usize, size: usize },
+    /// `offset + size` runs past the end of the accounts (`len`).
+    OutOfBounds { offset: usize, size: usize, len: usize },
+}
+
+impl Display for AppendVecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppendVecError::Overflow { offset, size } => {
+                write!(f, "Account read overflows; offset={offset}; size={size}")
+            }
+            AppendVecError::OutOfBounds { offset, size, len } => {
+                write!(f, "Account read out of bounds; offset={offset}; size={size}; len={len}")
+            }
+        }
+    }
+}
+
+impl std::error::Error for AppendVecError {}
@@ -0,0 +1,517 @@
+use std::fmt::Display;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use arrow::array::{ArrayBuilder, ArrayRef, BooleanBuilder, StringBuilder, UInt64Builder};
+use arrow::datatypes::{DataType, Field, Schema, SchemaRef};
+use arrow::record_batch::RecordBatch;
+use base64::prelude::{Engine, BASE64_STANDARD};
+use hashbrown::{HashMap, HashSet};
+use parquet::arrow::ArrowWriter;
+use parquet::basic::{Compression, ZstdLevel};
+use parquet::file::properties::WriterProperties;
+use serde_json::json;
+use solana_account_decoder::{encode_ui_account, UiAccountEncoding};
+use solana_sdk::pubkey::Pubkey;
+use solana_sdk::rent_collector::RENT_EXEMPT_RENT_EPOCH;
+use tracing::info;
+
+use crate::append_vec::StoredAccountMeta;
+use crate::args::{ExportArgs, ExportFormat};
+use crate::rpc::HistoricalRpc;
+
+/// Number of leading pubkey characters used to shard per-account files into
+/// subdirectories.
+const SHARD_PREFIX_LEN: usize = 2;
+/// Exported `rent_epoch` of rent exempt accounts under
+/// `--normalize-rent-epoch`.
+const NORMALIZED_RENT_EXEMPT_RENT_EPOCH: u64 = 0;
+/// Rows buffered before being written to the Parquet file as a record batch.
+const PARQUET_BATCH_ROWS: usize = 64 * 1024;
+
+pub(crate) fn export(rpc: &HistoricalRpc, args: &ExportArgs) {
+    let rewrites = Rewrites {
+        owners: args.owner_rename.iter().copied().collect(),
+        normalize_rent_epoch: args.normalize_rent_epoch,
+    };
+    let accounts = match args.format {
+        ExportFormat::Files => export_files(rpc, args, &rewrites),
+        ExportFormat::TestValidator => export_test_validator(rpc, args, &rewrites),
+        ExportFormat::Csv => export_csv(rpc, args, &rewrites),
+        ExportFormat::Parquet => export_parquet(rpc, args, &rewrites),
+    };
+
+    info!(accounts, out = ?args.out, "Export complete");
+}
+
+/// Writes `<out>/<prefix>/<pubkey>.bin` (account data) & `<pubkey>.json`
+/// (account metadata) for every account (zero lamport accounts are only
+/// written under `--emit-empty-accounts`).
+fn export_files(rpc: &HistoricalRpc, args: &ExportArgs, rewrites: &Rewrites) -> u64 {
+    let mut shards = HashSet::new();
+    let mut accounts = 0;
+    for (slot, _, account) in rpc.newest_accounts() {
+        let account = account.access().unwrap();
+        let Some(deleted) = exported(args, &account) else {
+            continue;
+        };
+        let pubkey = account.meta.pubkey.to_string();
+
+        let shard = args.out.join(&pubkey[..SHARD_PREFIX_LEN]);
+        if !shards.contains(&shard) {
+            std::fs::create_dir_all(&shard).unwrap();
+            shards.insert(shard.clone());
+        }
+
+        let metadata = json!({
+            "pubkey": pubkey,
+            "owner": rewrites.owner(&account.account_meta.owner).to_string(),
+            "lamports": account.account_meta.lamports,
+            "executable": account.account_meta.executable,
+            "rent_epoch": rewrites.rent_epoch(account.account_meta.rent_epoch),
+            "data_len": account.data.len(),
+            "slot": slot,
+            "deleted": deleted,
+        });
+        write_file(&shard.join(format!("{pubkey}.bin")), args.output_buffer_size, |writer| {
+            writer.write_all(account.data)
+        });
+        write_file(&shard.join(format!("{pubkey}.json")), args.output_buffer_size, |writer| {
+            Ok(serde_json::to_writer_pretty(writer, &metadata)?)
+        });
+
+        accounts += 1;
+    }
+
+    accounts
+}
+
+/// Writes `<out>/<pubkey>.json` for every account in the format accepted by
+/// `solana-test-validator --account-dir <out>` (zero lamport accounts are only
+/// written under `--emit-empty-accounts`).
+fn export_test_validator(rpc: &HistoricalRpc, args: &ExportArgs, rewrites: &Rewrites) -> u64 {
+    std::fs::create_dir_all(&args.out).unwrap();
+
+    let mut accounts = 0;
+    for (_, _, account) in rpc.newest_accounts() {
+        let account = account.access().unwrap();
+        if exported(args, &account).is_none() {
+            continue;
+        }
+        let pubkey = account.meta.pubkey;
+        let mut account = account.clone_account();
+        account.owner = rewrites.owner(&account.owner);
+        account.rent_epoch = rewrites.rent_epoch(account.rent_epoch);
+
+        let account = encode_ui_account(&pubkey, &account, UiAccountEncoding::Base64, None, None);
+        let account = json!({ "pubkey": pubkey.to_string(), "account": account });
+        write_file(&args.out.join(format!("{pubkey}.json")), args.output_buffer_size, |writer| {
+            Ok(serde_json::to_writer_pretty(writer, &account)?)
+        });
+
+        accounts += 1;
+    }
+
+    accounts
+}
+
+/// Writes a single CSV file to `<out>` with one row per account:
+/// `pubkey,owner,lamports,executable,rent_epoch,data_len,slot,deleted` (&
+/// `data`, base64 encoded, under `--include-data`). Zero lamport accounts are
+/// only written under `--emit-empty-accounts`.
+fn export_csv(rpc: &HistoricalRpc, args: &ExportArgs, rewrites: &Rewrites) -> u64 {
+    if let Some(parent) = args.out.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+
+    let mut accounts = 0;
+    write_file(&args.out, args.output_buffer_size, |writer| {
+        write!(writer, "pubkey,owner,lamports,executable,rent_epoch,data_len,slot,deleted")?;
+        if args.include_data {
+            write!(writer, ",data")?;
+        }
+        writeln!(writer)?;
+
+        for (slot, _, account) in rpc.newest_accounts() {
+            let account = account.access().unwrap();
+            let Some(deleted) = exported(args, &account) else {
+                continue;
+            };
+
+            write!(
+                writer,
+                "{},{},{},{},{},{},{slot},{deleted}",
+                account.meta.pubkey,
+                rewrites.owner(&account.account_meta.owner),
+                account.account_meta.lamports,
+                account.account_meta.executable,
+                rewrites.rent_epoch(account.account_meta.rent_epoch),
+                account.data.len(),
+            )?;
+            if args.include_data {
+                write!(writer, ",{}", BASE64_STANDARD.encode(account.data))?;
+            }
+            writeln!(writer)?;
+
+            accounts += 1;
+        }
+
+        Ok(())
+    });
+
+    accounts
+}
+
+/// Writes a single Parquet file (zstd compressed) to `<out>` with the same
+/// columns as [`export_csv`] (`data` is base64 encoded & only written under
+/// `--include-data`). Rows are written in batches of [`PARQUET_BATCH_ROWS`], so
+/// memory stays bounded regardless of the snapshot size.
+fn export_parquet(rpc: &HistoricalRpc, args: &ExportArgs, rewrites: &Rewrites) -> u64 {
+    if let Some(parent) = args.out.parent() {
+        std::fs::create_dir_all(parent).unwrap();
+    }
+    let path = &args.out;
+
+    let mut batch = ParquetBatch::new(args.include_data);
+    let file = BufWriter::with_capacity(args.output_buffer_size, File::create(path).unwrap());
+    let properties = WriterProperties::builder()
+        .set_compression(Compression::ZSTD(ZstdLevel::default()))
+        .build();
+    let mut writer = ArrowWriter::try_new(file, batch.schema.clone(), Some(properties))
+        .unwrap_or_else(|err| export_failed(path, err));
+
+    let mut accounts = 0;
+    for (slot, _, account) in rpc.newest_accounts() {
+        let account = account.access().unwrap();
+        let Some(deleted) = exported(args, &account) else {
+            continue;
+        };
+
+        batch.pubkey.append_value(account.meta.pubkey.to_string());
+        batch
+            .owner
+            .append_value(rewrites.owner(&account.account_meta.owner).to_string());
+        batch.lamports.append_value(account.account_meta.lamports);
+        batch
+            .executable
+            .append_value(account.account_meta.executable);
+        batch
+            .rent_epoch
+            .append_value(rewrites.rent_epoch(account.account_meta.rent_epoch));
+        batch.data_len.append_value(account.data.len() as u64);
+        batch.slot.append_value(slot);
+        batch.deleted.append_value(deleted);
+        if let Some(data) = &mut batch.data {
+            data.append_value(BASE64_STANDARD.encode(account.data));
+        }
+        accounts += 1;
+
+        if batch.lamports.len() == PARQUET_BATCH_ROWS {
+            writer
+                .write(&batch.finish())
+                .unwrap_or_else(|err| export_failed(path, err));
+        }
+    }
+    if !batch.lamports.is_empty() {
+        writer
+            .write(&batch.finish())
+            .unwrap_or_else(|err| export_failed(path, err));
+    }
+    let mut file = writer
+        .into_inner()
+        .unwrap_or_else(|err| export_failed(path, err));
+    file.flush().unwrap_or_else(|err| export_failed(path, err));
+
+    accounts
+}
+
+/// The columns of the Parquet export rows buffered since the last record
+/// batch.
+struct ParquetBatch {
+    schema: SchemaRef,
+    pubkey: StringBuilder,
+    owner: StringBuilder,
+    lamports: UInt64Builder,
+    executable: BooleanBuilder,
+    rent_epoch: UInt64Builder,
+    data_len: UInt64Builder,
+    slot: UInt64Builder,
+    deleted: BooleanBuilder,
+    /// Set under `--include-data`.
+    data: Option<StringBuilder>,
+}
+
+impl ParquetBatch {
+    fn new(include_data: bool) -> Self {
+        let mut fields = vec![
+            Field::new("pubkey", DataType::Utf8, false),
+            Field::new("owner", DataType::Utf8, false),
+            Field::new("lamports", DataType::UInt64, false),
+            Field::new("executable", DataType::Boolean, false),
+            Field::new("rent_epoch", DataType::UInt64, false),
+            Field::new("data_len", DataType::UInt64, false),
+            Field::new("slot", DataType::UInt64, false),
+            Field::new("deleted", DataType::Boolean, false),
+        ];
+        if include_data {
+            fields.push(Field::new("data", DataType::Utf8, false));
+        }
+
+        ParquetBatch {
+            schema: Arc::new(Schema::new(fields)),
+            pubkey: StringBuilder::new(),
+            owner: StringBuilder::new(),
+            lamports: UInt64Builder::new(),
+            executable: BooleanBuilder::new(),
+            rent_epoch: UInt64Builder::new(),
+            data_len: UInt64Builder::new(),
+            slot: UInt64Builder::new(),
+            deleted: BooleanBuilder::new(),
+            data: include_data.then(StringBuilder::new),
+        }
+    }
+
+    /// Takes the buffered rows as a record batch.
+    fn finish(&mut self) -> RecordBatch {
+        let mut columns: Vec<ArrayRef> = vec![
+            Arc::new(self.pubkey.finish()),
+            Arc::new(self.owner.finish()),
+            Arc::new(self.lamports.finish()),
+            Arc::new(self.executable.finish()),
+            Arc::new(self.rent_epoch.finish()),
+            Arc::new(self.data_len.finish()),
+            Arc::new(self.slot.finish()),
+            Arc::new(self.deleted.finish()),
+        ];
+        if let Some(data) = &mut self.data {
+            columns.push(Arc::new(data.finish()));
+        }
+
+        RecordBatch::try_new(self.schema.clone(), columns).unwrap()
+    }
+}
+
+/// Returns whether `account` is deleted (has zero lamports), or `None` if it
+/// should not be exported (deleted accounts require `--emit-empty-accounts`).
+fn exported(args: &ExportArgs, account: &StoredAccountMeta) -> Option<bool> {
+    let deleted = account.account_meta.lamports == 0;
+
+    (!deleted || args.emit_empty_accounts).then_some(deleted)
+}
+
+/// Creates `path` & writes it through a `buffer_size` byte buffer.
+fn write_file(
+    path: &Path,
+    buffer_size: usize,
+    write: impl FnOnce(&mut BufWriter<File>) -> std::io::Result<()>,
+) {
+    let mut writer = BufWriter::with_capacity(buffer_size, File::create(path).unwrap());
+    write(&mut writer)
+        .and_then(|()| writer.flush())
+        .unwrap_or_else(|err| export_failed(path, err));
+}
+
+fn export_failed(path: &Path, err: impl Display) -> ! {
+    panic!("Failed to write export; path={path:?}; err={err}")
+}
+
+/// Field rewrites applied to exported records (never to account data).
+struct Rewrites {
+    /// See `--owner-rename`.
+    owners: HashMap<Pubkey, Pubkey>,
+    /// See `--normalize-rent-epoch`.
+    normalize_rent_epoch: bool,
+}
+
+impl Rewrites {
+    fn owner(&self, owner: &Pubkey) -> Pubkey {
+        self.owners.get(owner).copied().unwrap_or(*owner)
+    }
+
+    const fn rent_epoch(&self, rent_epoch: u64) -> u64 {
+        if self.normalize_rent_epoch && rent_epoch == RENT_EXEMPT_RENT_EPOCH {
+            NORMALIZED_RENT_EXEMPT_RENT_EPOCH
+        } else {
+            rent_epoch
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use arrow::array::{Array, StringArray, UInt64Array};
+    use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+    use super::*;
+    use crate::args::Action;
+    use crate::test_utils::{load_snapshot, parse_args, SnapshotBuilder, TestAccount};
+
+    #[test]
+    fn csv_marks_deleted_accounts() {
+        let (live, closed) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let owner = Pubkey::new_unique();
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(9, 0, vec![TestAccount::new(closed, 5, owner, vec![])])
+            .append_vec(
+                10,
+                0,
+                vec![
+                    TestAccount::new(live, 5, owner, vec![1]),
+                    TestAccount::new(closed, 0, owner, vec![]),
+                ],
+            )
+            .write();
+        let rpc = load_snapshot(snapshot.path(), &[]);
+        let out = snapshot.path().join("export/accounts.csv");
+        let out_str = out.to_str().unwrap();
+        let export_args = |emit_empty_accounts: &[&str]| {
+            let args: Vec<_> =
+                ["--source", "unused", "export", "--format", "csv", "--out", out_str]
+                    .into_iter()
+                    .chain(emit_empty_accounts.iter().copied())
+                    .collect();
+            match parse_args(&args).action {
+                Action::Export(export_args) => export_args,
+                _ => unreachable!(),
+            }
+        };
+
+        export(&rpc, &export_args(&[]));
+        let csv = std::fs::read_to_string(&out).unwrap();
+        assert_eq!(
+            csv,
+            format!(
+                "pubkey,owner,lamports,executable,rent_epoch,data_len,slot,deleted\n{live},\
+                 {owner},5,false,0,1,10,false\n"
+            )
+        );
+
+        export(&rpc, &export_args(&["--emit-empty-accounts"]));
+        let csv = std::fs::read_to_string(&out).unwrap();
+        let mut rows: Vec<_> = csv.lines().skip(1).collect();
+        rows.sort_unstable_by_key(|row| !row.starts_with(&live.to_string()));
+        assert_eq!(
+            rows,
+            [
+                format!("{live},{owner},5,false,0,1,10,false"),
+                format!("{closed},{owner},0,false,0,0,10,true"),
+            ]
+        );
+    }
+
+    #[test]
+    fn csv_rows_with_data() {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<_> = (1..=20u8)
+            .map(|i| TestAccount::new(Pubkey::new_unique(), i.into(), owner, vec![i; i.into()]))
+            .collect();
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(10, 0, accounts.clone())
+            .write();
+        let rpc = load_snapshot(snapshot.path(), &[]);
+        let out = snapshot.path().join("accounts.csv");
+        let args = parse_args(&[
+            "--source",
+            "unused",
+            "export",
+            "--format",
+            "csv",
+            "--include-data",
+            "--out",
+            out.to_str().unwrap(),
+        ]);
+        let Action::Export(export_args) = args.action else {
+            unreachable!();
+        };
+
+        export(&rpc, &export_args);
+
+        let csv = std::fs::read_to_string(&out).unwrap();
+        let mut lines = csv.lines();
+        assert_eq!(
+            lines.next(),
+            Some("pubkey,owner,lamports,executable,rent_epoch,data_len,slot,deleted,data")
+        );
+        let rows: HashMap<_, _> = lines
+            .map(|row| {
+                let columns: Vec<_> = row.split(',').collect();
+                assert_eq!(columns.len(), 9);
+
+                (columns[0].to_string(), columns[8].to_string())
+            })
+            .collect();
+        assert_eq!(rows.len(), accounts.len());
+        for TestAccount { pubkey, account, .. } in &accounts {
+            assert_eq!(rows[&pubkey.to_string()], BASE64_STANDARD.encode(&account.data));
+        }
+    }
+
+    #[test]
+    fn parquet_rows_with_data() {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<_> = (1..=20u8)
+            .map(|i| TestAccount::new(Pubkey::new_unique(), i.into(), owner, vec![i; i.into()]))
+            .collect();
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(10, 0, accounts.clone())
+            .write();
+        let rpc = load_snapshot(snapshot.path(), &[]);
+        let out = snapshot.path().join("export/accounts.parquet");
+        let args = parse_args(&[
+            "--source",
+            "unused",
+            "export",
+            "--format",
+            "parquet",
+            "--include-data",
+            "--out",
+            out.to_str().unwrap(),
+        ]);
+        let Action::Export(export_args) = args.action else {
+            unreachable!();
+        };
+
+        export(&rpc, &export_args);
+
+        let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(&out).unwrap()).unwrap();
+        assert_eq!(reader.metadata().file_metadata().num_rows(), accounts.len() as i64);
+        let mut rows = HashMap::new();
+        for batch in reader.build().unwrap() {
+            let batch = batch.unwrap();
+            assert_eq!(batch.num_columns(), 9);
+            let column = |name| {
+                batch
+                    .column_by_name(name)
+                    .unwrap()
+                    .as_any()
+                    .downcast_ref::<StringArray>()
+                    .unwrap()
+                    .clone()
+            };
+            let (pubkeys, owners, data) = (column("pubkey"), column("owner"), column("data"));
+            let lamports = batch
+                .column_by_name("lamports")
+                .unwrap()
+                .as_any()
+                .downcast_ref::<UInt64Array>()
+                .unwrap()
+                .clone();
+            for row in 0..batch.num_rows() {
+                assert_eq!(owners.value(row), owner.to_string());
+                rows.insert(
+                    pubkeys.value(row).to_string(),
+                    (lamports.value(row), data.value(row).to_string()),
+                );
+            }
+        }
+        assert_eq!(rows.len(), accounts.len());
+        for TestAccount { pubkey, account, .. } in &accounts {
+            assert_eq!(
+                rows[&pubkey.to_string()],
+                (account.lamports, BASE64_STANDARD.encode(&account.data))
+            );
+        }
+    }
+}
@@ -0,0 +1,36 @@
+use solana_sdk::pubkey::Pubkey;
+
+use crate::append_vec::StoredAccountMeta;
+
+/// Predicates applied to accounts during a scan, before any account data is
+/// copied. The index applies them to the version of each account it resolves
+/// (so an account is dropped if its newest version fails, even if an older
+/// version passes), the streaming commands to every stored version.
+#[derive(Debug, Default, Clone, clap::Args)]
+pub(crate) struct AccountFilter {
+    /// Skip accounts with less than this many bytes of data.
+    #[clap(long)]
+    pub(crate) min_data_len: Option<u64>,
+    /// Skip accounts with more than this many bytes of data.
+    #[clap(long)]
+    pub(crate) max_data_len: Option<u64>,
+    /// Skip accounts with less than this many lamports.
+    #[clap(long)]
+    pub(crate) min_lamports: Option<u64>,
+    /// Only include accounts owned by this program (repeatable).
+    #[clap(long = "owner")]
+    pub(crate) owners: Vec<Pubkey>,
+}
+
+impl AccountFilter {
+    pub(crate) fn matches(&self, account: &StoredAccountMeta) -> bool {
+        let data_len = account.meta.data_len;
+
+        self.min_data_len.is_none_or(|min| data_len >= min)
+            && self.max_data_len.is_none_or(|max| data_len <= max)
+            && self
+                .min_lamports
+                .is_none_or(|min| account.account_meta.lamports >= min)
+            && (self.owners.is_empty() || self.owners.contains(&account.account_meta.owner))
+    }
+}
@@ -0,0 +1,86 @@
+use std::io::{BufWriter, ErrorKind, Write};
+
+use memchr::memmem::Finder;
+use tracing::info;
+
+use crate::filter::AccountFilter;
+use crate::unpacked::UnpackedSnapshotExtractor;
+use crate::utils::accounts_with_location;
+
+/// Prints `<pubkey> <offset> <slot>` for every stored account version (not
+/// only the newest) matching `filter` whose data contains `pattern`, where
+/// `offset` is the position of the first match.
+///
+/// Accounts are streamed from the append vecs without building an index, so
+/// memory use does not grow with the snapshot.
+pub(crate) fn grep(extractor: &UnpackedSnapshotExtractor, filter: &AccountFilter, pattern: &[u8]) {
+    grep_to(extractor, filter, pattern, std::io::stdout().lock());
+}
+
+fn grep_to(
+    extractor: &UnpackedSnapshotExtractor,
+    filter: &AccountFilter,
+    pattern: &[u8],
+    out: impl Write,
+) {
+    let finder = Finder::new(pattern);
+    let mut out = BufWriter::new(out);
+
+    let mut accounts = 0;
+    let mut matches = 0;
+    for (slot, _, account) in accounts_with_location(extractor) {
+        let account = account.access().unwrap();
+        if !filter.matches(&account) {
+            continue;
+        }
+        accounts += 1;
+
+        let Some(offset) = finder.find(account.data) else {
+            continue;
+        };
+        matches += 1;
+        match writeln!(out, "{} {offset} {slot}", account.meta.pubkey) {
+            Ok(()) => {}
+            // NB: The reader exited (e.g. `| head`), so stop quietly.
+            Err(err) if err.kind() == ErrorKind::BrokenPipe => return,
+            Err(err) => panic!("Failed to write matches; err={err}"),
+        }
+    }
+
+    let _ = out.flush();
+    extractor.log_scan_errors();
+    info!(accounts, matches, "Search complete");
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::test_utils::{open_snapshot, SnapshotBuilder, TestAccount};
+
+    #[test]
+    fn grep_streams_matching_accounts() {
+        let owner = Pubkey::new_unique();
+        let other_owner = Pubkey::new_unique();
+        let matching = Pubkey::new_unique();
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(
+                9,
+                0,
+                vec![
+                    TestAccount::new(matching, 1, owner, vec![0, 0, 0xde, 0xad]),
+                    TestAccount::new(Pubkey::new_unique(), 1, owner, vec![0xde, 0xaa]),
+                    TestAccount::new(Pubkey::new_unique(), 1, other_owner, vec![0xde, 0xad]),
+                ],
+            )
+            .write();
+        let extractor = open_snapshot(snapshot.path(), &[]);
+        let filter = AccountFilter { owners: vec![owner], ..Default::default() };
+
+        let mut out = Vec::new();
+        grep_to(&extractor, &filter, &[0xde, 0xad], &mut out);
+
+        assert_eq!(String::from_utf8(out).unwrap(), format!("{matching} 2 9\n"));
+    }
+}
@@ -0,0 +1,55 @@
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use tracing::info;
+
+use crate::unpacked::UnpackedSnapshotExtractor;
+use crate::utils::accounts_with_location;
+
+/// Totals of a single slot's append vecs.
+#[derive(Debug, Default)]
+struct SlotStats {
+    append_vecs: usize,
+    accounts: u64,
+    data_bytes: u64,
+}
+
+/// Writes a CSV of `slot,append_vec_count,account_count,total_bytes` to `out`
+/// (one row per slot in the manifest). Counts every stored account version,
+/// not only the newest, & `total_bytes` is the sum of the account data lengths.
+pub(crate) fn slot_histogram(extractor: &UnpackedSnapshotExtractor, out: &Path) {
+    let mut slots: BTreeMap<_, _> = extractor
+        .accounts_db_fields()
+        .0
+        .iter()
+        .map(|(slot, entries)| {
+            (*slot, SlotStats { append_vecs: entries.len(), ..SlotStats::default() })
+        })
+        .collect();
+    for (slot, _, account) in accounts_with_location(extractor) {
+        let account = account.access().unwrap();
+        let stats = slots.entry(slot).or_default();
+        stats.accounts += 1;
+        stats.data_bytes += account.data.len() as u64;
+    }
+    extractor.log_scan_errors();
+
+    let mut writer = BufWriter::new(File::create(out).unwrap());
+    writeln!(writer, "slot,append_vec_count,account_count,total_bytes")
+        .and_then(|()| {
+            for (slot, stats) in &slots {
+                writeln!(
+                    writer,
+                    "{slot},{},{},{}",
+                    stats.append_vecs, stats.accounts, stats.data_bytes
+                )?;
+            }
+
+            writer.flush()
+        })
+        .unwrap_or_else(|err| panic!("Failed to write slot histogram; path={out:?}; err={err}"));
+
+    info!(slots = slots.len(), ?out, "Slot histogram written");
+}
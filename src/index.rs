@@ -0,0 +1,383 @@
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
+
+use hashbrown::hash_map::Entry;
+use hashbrown::HashMap;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::error::IndexFileError;
+
+/// Number of low bits of a [`PackedLocation`] used to store the append vec id.
+const PACKED_ID_BITS: u32 = 24;
+/// Largest slot representable by a [`PackedLocation`] (~1.1 trillion).
+pub(crate) const PACKED_MAX_SLOT: u64 = (1 << (64 - PACKED_ID_BITS)) - 1;
+/// Largest append vec id representable by a [`PackedLocation`] (~16.7 million).
+pub(crate) const PACKED_MAX_ID: u64 = (1 << PACKED_ID_BITS) - 1;
+
+/// Magic bytes identifying a persisted index file.
+const INDEX_FILE_MAGIC: [u8; 4] = *b"SSEI";
+/// Current persisted index file format version.
+const INDEX_FILE_VERSION: u16 = 2;
+/// Persisted index scheme byte for [`AccountIndex::Wide`].
+const SCHEME_WIDE: u8 = 0;
+/// Persisted index scheme byte for [`AccountIndex::Packed`].
+const SCHEME_PACKED: u8 = 1;
+/// Length of the persisted index header (magic, version, slot, count & scheme).
+const INDEX_FILE_HEADER_LEN: u64 = 4 + 2 + 8 + 8 + 1;
+/// Length of a persisted [`AccountIndex::Wide`] record.
+const WIDE_RECORD_LEN: u64 = 32 + 3 * 8;
+/// Length of a persisted [`AccountIndex::Packed`] record.
+const PACKED_RECORD_LEN: u64 = 32 + 8;
+
+/// Maps each account to the `(slot, id)` of the append vec holding its newest
+/// version.
+pub(crate) enum AccountIndex {
+    /// Stores the location & the account's offset within the append vec as
+    /// three words (24 bytes per entry).
+    Wide(HashMap<Pubkey, (u64, u64, usize)>),
+    /// Stores the location packed into a single `u64` (8 bytes per entry).
+    Packed(HashMap<Pubkey, PackedLocation>),
+}
+
+impl AccountIndex {
+    pub(crate) fn with_capacity(packed: bool, capacity: usize) -> Self {
+        if packed {
+            AccountIndex::Packed(HashMap::with_capacity(capacity))
+        } else {
+            AccountIndex::Wide(HashMap::with_capacity(capacity))
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        match self {
+            AccountIndex::Wide(index) => index.len(),
+            AccountIndex::Packed(index) => index.len(),
+        }
+    }
+
+    pub(crate) fn keys(&self) -> impl Iterator<Item = &Pubkey> {
+        let (wide, packed) = match self {
+            AccountIndex::Wide(index) => (Some(index.keys()), None),
+            AccountIndex::Packed(index) => (None, Some(index.keys())),
+        };
+
+        wide.into_iter()
+            .flatten()
+            .chain(packed.into_iter().flatten())
+    }
+
+    pub(crate) fn get(&self, key: &Pubkey) -> Option<(u64, u64)> {
+        match self {
+            AccountIndex::Wide(index) => index.get(key).map(IndexValue::location),
+            AccountIndex::Packed(index) => index.get(key).map(IndexValue::location),
+        }
+    }
+
+    /// Returns the `(slot, id)` of `key` & its offset within the append vec (if
+    /// stored, the packed scheme does not store offsets).
+    pub(crate) fn get_with_offset(&self, key: &Pubkey) -> Option<(u64, u64, Option<usize>)> {
+        match self {
+            AccountIndex::Wide(index) => index
+                .get(key)
+                .map(|&(slot, id, offset)| (slot, id, Some(offset))),
+            AccountIndex::Packed(index) => index.get(key).map(|location| {
+                let (slot, id) = location.unpack();
+
+                (slot, id, None)
+            }),
+        }
+    }
+
+    /// Persists the index to `path` in the following format (integers are
+    /// little endian):
+    ///
+    /// | field     | encoding                                  |
+    /// |-----------|-------------------------------------------|
+    /// | `magic`   | `SSEI`                                    |
+    /// | `version` | `u16`                                     |
+    /// | `slot`    | `u64` (snapshot slot)                     |
+    /// | `count`   | `u64`                                     |
+    /// | `scheme`  | `u8` (0 = wide, 1 = packed)               |
+    /// | `records` | `count` * (`pubkey`, location)            |
+    ///
+    /// Each record's location is `(slot: u64, id: u64, offset: u64)` for the
+    /// wide scheme or a single [`PackedLocation`] `u64` for the packed scheme.
+    /// Records are sorted by pubkey so the same index always produces the same
+    /// file, regardless of how many threads built it.
+    pub(crate) fn write_to(&self, path: &Path, slot: u64) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writer.write_all(&INDEX_FILE_MAGIC)?;
+        writer.write_all(&INDEX_FILE_VERSION.to_le_bytes())?;
+        writer.write_all(&slot.to_le_bytes())?;
+        writer.write_all(&(self.len() as u64).to_le_bytes())?;
+        match self {
+            AccountIndex::Wide(index) => {
+                writer.write_all(&[SCHEME_WIDE])?;
+                let mut records: Vec<_> = index.iter().collect();
+                records.sort_unstable_by_key(|(key, _)| *key);
+                for (key, (indexed_slot, id, offset)) in records {
+                    writer.write_all(key.as_ref())?;
+                    writer.write_all(&indexed_slot.to_le_bytes())?;
+                    writer.write_all(&id.to_le_bytes())?;
+                    writer.write_all(&(*offset as u64).to_le_bytes())?;
+                }
+            }
+            AccountIndex::Packed(index) => {
+                writer.write_all(&[SCHEME_PACKED])?;
+                let mut records: Vec<_> = index.iter().collect();
+                records.sort_unstable_by_key(|(key, _)| *key);
+                for (key, location) in records {
+                    writer.write_all(key.as_ref())?;
+                    writer.write_all(&location.0.to_le_bytes())?;
+                }
+            }
+        }
+
+        writer.flush()
+    }
+
+    /// Loads an index persisted by [`AccountIndex::write_to`], rejecting files
+    /// with an unknown format, that were built from a different snapshot slot
+    /// or whose length does not match their record count.
+    pub(crate) fn read_from(path: &Path, slot: u64) -> Result<Self, IndexFileError> {
+        let file = File::open(path)?;
+        let file_len = file.metadata()?.len();
+        let mut reader = BufReader::new(file);
+
+        let magic: [u8; 4] = read_array(&mut reader)?;
+        if magic != INDEX_FILE_MAGIC {
+            return Err(IndexFileError::InvalidMagic(magic));
+        }
+        let version = u16::from_le_bytes(read_array(&mut reader)?);
+        if version != INDEX_FILE_VERSION {
+            return Err(IndexFileError::UnsupportedVersion(version));
+        }
+        let index_slot = u64::from_le_bytes(read_array(&mut reader)?);
+        if index_slot != slot {
+            return Err(IndexFileError::SlotMismatch { expected: slot, found: index_slot });
+        }
+        let count = u64::from_le_bytes(read_array(&mut reader)?);
+        let [scheme] = read_array(&mut reader)?;
+
+        // NB: Validate the count before sizing the index by it.
+        let record_len = match scheme {
+            SCHEME_WIDE => WIDE_RECORD_LEN,
+            SCHEME_PACKED => PACKED_RECORD_LEN,
+            scheme => return Err(IndexFileError::UnknownScheme(scheme)),
+        };
+        let records_len = file_len.saturating_sub(INDEX_FILE_HEADER_LEN);
+        if count.checked_mul(record_len) != Some(records_len) {
+            return Err(IndexFileError::LengthMismatch { count, records_len });
+        }
+        let count = count as usize;
+
+        match scheme {
+            SCHEME_WIDE => {
+                let mut index = HashMap::with_capacity(count);
+                for _ in 0..count {
+                    let key = Pubkey::new_from_array(read_array(&mut reader)?);
+                    let indexed_slot = u64::from_le_bytes(read_array(&mut reader)?);
+                    let id = u64::from_le_bytes(read_array(&mut reader)?);
+                    let offset = u64::from_le_bytes(read_array(&mut reader)?) as usize;
+                    index.insert(key, (indexed_slot, id, offset));
+                }
+
+                Ok(AccountIndex::Wide(index))
+            }
+            SCHEME_PACKED => {
+                let mut index = HashMap::with_capacity(count);
+                for _ in 0..count {
+                    let key = Pubkey::new_from_array(read_array(&mut reader)?);
+                    let location = PackedLocation(u64::from_le_bytes(read_array(&mut reader)?));
+                    index.insert(key, location);
+                }
+
+                Ok(AccountIndex::Packed(index))
+            }
+            // NB: Unknown schemes were rejected before reading the records.
+            _ => unreachable!(),
+        }
+    }
+
+    /// Removes `key`, returning whether it was indexed.
+    pub(crate) fn remove(&mut self, key: &Pubkey) -> bool {
+        match self {
+            AccountIndex::Wide(index) => index.remove(key).is_some(),
+            AccountIndex::Packed(index) => index.remove(key).is_some(),
+        }
+    }
+
+    /// Inserts the location (the account is stored at `offset` within the
+    /// append vec) if `key` is new, or if `replace` returns `true` when called
+    /// with the currently indexed `(slot, id)`.
+    pub(crate) fn upsert(
+        &mut self,
+        key: Pubkey,
+        (slot, id, offset): (u64, u64, usize),
+        replace: impl FnOnce(u64, u64) -> bool,
+    ) -> Upsert {
+        match self {
+            AccountIndex::Wide(index) => upsert(index, key, (slot, id, offset), replace),
+            AccountIndex::Packed(index) => upsert(index, key, (slot, id, offset), replace),
+        }
+    }
+}
+
+/// Outcome of [`AccountIndex::upsert`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Upsert {
+    Inserted,
+    Replaced,
+    Retained,
+}
+
+fn upsert<V: IndexValue>(
+    index: &mut HashMap<Pubkey, V>,
+    key: Pubkey,
+    (slot, id, offset): (u64, u64, usize),
+    replace: impl FnOnce(u64, u64) -> bool,
+) -> Upsert {
+    match index.entry(key) {
+        Entry::Occupied(mut entry) => {
+            let (indexed_slot, indexed_id) = entry.get().location();
+            if !replace(indexed_slot, indexed_id) {
+                return Upsert::Retained;
+            }
+
+            entry.insert(V::new(slot, id, offset));
+
+            Upsert::Replaced
+        }
+        Entry::Vacant(entry) => {
+            entry.insert(V::new(slot, id, offset));
+
+            Upsert::Inserted
+        }
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> std::io::Result<[u8; N]> {
+    let mut buf = [0; N];
+    reader.read_exact(&mut buf)?;
+
+    Ok(buf)
+}
+
+trait IndexValue: Copy {
+    fn new(slot: u64, id: u64, offset: usize) -> Self;
+
+    fn location(&self) -> (u64, u64);
+}
+
+impl IndexValue for (u64, u64, usize) {
+    fn new(slot: u64, id: u64, offset: usize) -> Self {
+        (slot, id, offset)
+    }
+
+    fn location(&self) -> (u64, u64) {
+        (self.0, self.1)
+    }
+}
+
+/// A `(slot, id)` pair packed into a single `u64`; the slot occupies the high
+/// 40 bits & the append vec id the low 24 bits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct PackedLocation(u64);
+
+impl PackedLocation {
+    /// Packs `(slot, id)`, returning `None` if either does not fit.
+    pub(crate) const fn pack(slot: u64, id: u64) -> Option<Self> {
+        if slot > PACKED_MAX_SLOT || id > PACKED_MAX_ID {
+            return None;
+        }
+
+        Some(PackedLocation((slot << PACKED_ID_BITS) | id))
+    }
+
+    pub(crate) const fn unpack(self) -> (u64, u64) {
+        (self.0 >> PACKED_ID_BITS, self.0 & PACKED_MAX_ID)
+    }
+}
+
+impl IndexValue for PackedLocation {
+    fn new(slot: u64, id: u64, _: usize) -> Self {
+        // NB: Callers validate the manifest's slots & ids before building a packed
+        // index.
+        PackedLocation::pack(slot, id).unwrap()
+    }
+
+    fn location(&self) -> (u64, u64) {
+        self.unpack()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use super::*;
+
+    fn test_index(packed: bool) -> AccountIndex {
+        let mut index = AccountIndex::with_capacity(packed, 0);
+        for i in 0..100 {
+            index.upsert(Pubkey::new_unique(), (i, i % 7, i as usize * 136), |_, _| true);
+        }
+
+        index
+    }
+
+    #[test]
+    fn packed_location_round_trip() {
+        for (slot, id) in [(0, 0), (1, 2), (PACKED_MAX_SLOT, PACKED_MAX_ID)] {
+            assert_eq!(PackedLocation::pack(slot, id).unwrap().unpack(), (slot, id));
+        }
+        assert_eq!(PackedLocation::pack(PACKED_MAX_SLOT + 1, 0), None);
+        assert_eq!(PackedLocation::pack(0, PACKED_MAX_ID + 1), None);
+    }
+
+    #[test]
+    fn index_file_round_trip() {
+        for packed in [false, true] {
+            let index = test_index(packed);
+            let file = tempfile::NamedTempFile::new().unwrap();
+            index.write_to(file.path(), 10).unwrap();
+
+            let loaded = AccountIndex::read_from(file.path(), 10).unwrap();
+            assert_eq!(matches!(loaded, AccountIndex::Packed(_)), packed);
+            assert_eq!(loaded.len(), index.len());
+            for key in index.keys() {
+                assert_eq!(loaded.get_with_offset(key), index.get_with_offset(key));
+            }
+
+            assert!(matches!(
+                AccountIndex::read_from(file.path(), 11),
+                Err(IndexFileError::SlotMismatch { expected: 11, found: 10 })
+            ));
+        }
+    }
+
+    #[test]
+    fn index_file_rejects_mismatched_count() {
+        let file = tempfile::NamedTempFile::new().unwrap();
+        test_index(true).write_to(file.path(), 10).unwrap();
+
+        // NB: Claim ~2^64 records without allocating for them.
+        let mut bytes = std::fs::read(file.path()).unwrap();
+        bytes[14..22].copy_from_slice(&u64::MAX.to_le_bytes());
+        std::fs::write(file.path(), &bytes).unwrap();
+        assert!(matches!(
+            AccountIndex::read_from(file.path(), 10),
+            Err(IndexFileError::LengthMismatch { count: u64::MAX, .. })
+        ));
+
+        // NB: Truncate a record.
+        bytes[14..22].copy_from_slice(&100u64.to_le_bytes());
+        let mut truncated = std::fs::File::create(file.path()).unwrap();
+        truncated.write_all(&bytes[..bytes.len() - 1]).unwrap();
+        assert!(matches!(
+            AccountIndex::read_from(file.path(), 10),
+            Err(IndexFileError::LengthMismatch { count: 100, .. })
+        ));
+    }
+}
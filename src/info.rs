@@ -0,0 +1,119 @@
+use std::fmt::Display;
+
+use serde::{Serialize, Serializer};
+use solana_sdk::clock::{Epoch, Slot, UnixTimestamp};
+use solana_sdk::epoch_schedule::EpochSchedule;
+use solana_sdk::fee_calculator::FeeRateGovernor;
+use solana_sdk::hash::Hash;
+use solana_sdk::inflation::Inflation;
+use solana_sdk::rent::Rent;
+
+use crate::unpacked::UnpackedSnapshotExtractor;
+
+/// The accounts hash scheme a snapshot was produced with, derived from the
+/// optional manifest fields following the accounts DB fields.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub(crate) enum AccountsHashKind {
+    /// The trailing manifest fields are absent (or could not be parsed).
+    Unknown,
+    /// Full snapshot merkle accounts hash.
+    Merkle,
+    /// Incremental snapshot merkle accounts hash (the manifest carries the
+    /// incremental snapshot persistence fields).
+    IncrementalMerkle,
+    /// Accounts lattice hash.
+    Lattice,
+}
+
+/// A curated subset of the manifest's bank fields, insulating consumers from
+/// the layout of [`crate::solana::DeserializableVersionedBank`].
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct BankFields {
+    pub(crate) slot: Slot,
+    pub(crate) epoch: Epoch,
+    pub(crate) block_height: u64,
+    pub(crate) capitalization: u64,
+    #[serde(serialize_with = "serialize_display")]
+    pub(crate) hash: Hash,
+    #[serde(serialize_with = "serialize_display")]
+    pub(crate) parent_hash: Hash,
+    pub(crate) genesis_creation_time: UnixTimestamp,
+    pub(crate) inflation: Inflation,
+    pub(crate) epoch_schedule: EpochSchedule,
+    pub(crate) fee_rate_governor: FeeRateGovernor,
+    pub(crate) rent: Rent,
+}
+
+fn serialize_display<S: Serializer>(
+    value: &impl Display,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_str(value)
+}
+
+/// Snapshot metadata that can be derived from the manifest alone (i.e. without
+/// scanning any accounts).
+#[derive(Debug, Serialize)]
+pub(crate) struct SnapshotInfo {
+    #[serde(flatten)]
+    bank: BankFields,
+    version: Option<String>,
+    append_vec_count: usize,
+    total_stored_bytes: u64,
+    /// Whether this is an incremental snapshot (from the manifest's
+    /// incremental snapshot persistence fields or `--incremental-source`).
+    incremental: bool,
+    /// Slot of the full snapshot an incremental snapshot was taken against
+    /// (if recorded).
+    full_snapshot_slot: Option<Slot>,
+    accounts_hash_kind: AccountsHashKind,
+}
+
+impl SnapshotInfo {
+    pub(crate) fn new(extractor: &UnpackedSnapshotExtractor) -> Self {
+        let storages = &extractor.accounts_db_fields().0;
+
+        SnapshotInfo {
+            bank: extractor.bank_fields(),
+            version: extractor.version().map(str::to_string),
+            append_vec_count: storages.values().map(|entries| entries.len()).sum(),
+            total_stored_bytes: storages
+                .values()
+                .flatten()
+                .map(|entry| entry.accounts_current_len as u64)
+                .sum(),
+            incremental: extractor.is_incremental(),
+            full_snapshot_slot: extractor.full_slot(),
+            accounts_hash_kind: extractor.accounts_hash_kind(),
+        }
+    }
+
+    pub(crate) fn print(&self, json: bool) {
+        if json {
+            println!("{}", serde_json::to_string_pretty(self).unwrap());
+        } else {
+            println!("{self:#?}");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::{open_snapshot, SnapshotBuilder};
+
+    #[test]
+    fn reports_incremental_snapshots() {
+        let full = SnapshotBuilder::new(10).write();
+        let info = SnapshotInfo::new(&open_snapshot(full.path(), &[]));
+        assert!(!info.incremental);
+        assert_eq!(info.full_snapshot_slot, None);
+
+        let incremental = SnapshotBuilder::new(12).incremental(10).write();
+        let info = SnapshotInfo::new(&open_snapshot(incremental.path(), &[]));
+        assert!(info.incremental);
+        assert_eq!(info.full_snapshot_slot, Some(10));
+        assert_eq!(info.accounts_hash_kind, AccountsHashKind::IncrementalMerkle);
+    }
+}
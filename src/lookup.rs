@@ -0,0 +1,29 @@
+use std::path::Path;
+
+use serde_json::json;
+use solana_account_decoder::{encode_ui_account, UiAccountEncoding};
+use solana_sdk::pubkey::Pubkey;
+
+use crate::rpc::HistoricalRpc;
+
+/// Prints `{"pubkey", "account"}` (base64 data, `null` if not found) as a
+/// JSON line for every pubkey in `pubkeys_file`, in file order.
+pub(crate) fn run(rpc: &HistoricalRpc, pubkeys_file: &Path) {
+    let keys: Vec<Pubkey> = std::fs::read_to_string(pubkeys_file)
+        .unwrap()
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+        .map(|line| {
+            line.parse()
+                .unwrap_or_else(|err| panic!("Invalid pubkey; line={line}; err={err}"))
+        })
+        .collect();
+
+    for (key, account) in keys.iter().zip(rpc.get_accounts(&keys)) {
+        let account = account
+            .map(|account| encode_ui_account(key, &account, UiAccountEncoding::Base64, None, None));
+
+        println!("{}", json!({ "pubkey": key.to_string(), "account": account }));
+    }
+}
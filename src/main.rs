@@ -2,34 +2,163 @@
 /// changed visibility & helper methods.
 mod append_vec;
 mod args;
+mod batch;
+mod checksum;
+mod dump;
+mod error;
+mod export;
+mod filter;
+mod grep;
+mod histogram;
+mod index;
+mod info;
+mod lookup;
+mod repl;
+mod rewards;
 mod rpc;
 mod solana;
+mod stats;
+#[cfg(test)]
+mod test_utils;
+mod token;
 mod unpacked;
 mod utils;
 
-fn main() {
-    use std::sync::mpsc;
+use std::sync::atomic::AtomicBool;
+
+use tracing::warn;
+
+use crate::args::{IndexArgs, ProgressArgs, RpcArgs};
+use crate::rpc::HistoricalRpc;
+use crate::unpacked::UnpackedSnapshotExtractor;
 
+fn main() {
     use clap::Parser;
-    use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
 
-    use crate::rpc::HistoricalRpc;
-    use crate::unpacked::UnpackedSnapshotExtractor;
+    use crate::args::Action;
+    use crate::info::SnapshotInfo;
     use crate::utils::LoadProgressTracking;
 
     let _ = toolbox::tracing::setup_tracing("solana-snapshot-etl", None);
 
     let args = args::Args::parse();
+    if let Action::Rpc(RpcArgs { list_methods: true, .. }) = args.action {
+        rpc::print_methods();
+
+        return;
+    }
+    args.source.require_source();
+    if let Some(source_list_file) = &args.source.source_list_file {
+        batch::run(&args, source_list_file);
+
+        return;
+    }
+
+    let loader = UnpackedSnapshotExtractor::open(
+        args.source.path.as_deref().unwrap(),
+        &args.source,
+        Box::new(LoadProgressTracking {
+            quiet: args.progress.quiet(),
+            style: args.progress.manifest_style(),
+        }),
+    );
+
+    match args.action {
+        Action::Rpc(rpc_args) => serve_rpc(loader, &args.index, &args.progress, rpc_args),
+        Action::Info { json } => SnapshotInfo::new(&loader).print(json),
+        Action::Repl => {
+            repl::run(&load_index(loader, &args.index, &args.progress, &AtomicBool::default()))
+        }
+        Action::Checksum => {
+            let (digest, accounts) = checksum::checksum(&load_index(
+                loader,
+                &args.index,
+                &args.progress,
+                &AtomicBool::default(),
+            ));
+
+            println!("{digest} ({accounts} accounts)");
+        }
+        Action::Export(export_args) => {
+            export::export(
+                &load_index(loader, &args.index, &args.progress, &AtomicBool::default()),
+                &export_args,
+            );
+        }
+        Action::Check => {
+            let errors = loader.verify_append_vec_files();
+            for error in &errors {
+                println!("{error}");
+            }
+
+            println!("Found {} invalid append vec(s)", errors.len());
+            if !errors.is_empty() {
+                std::process::exit(1);
+            }
+        }
+        Action::Lookup { pubkeys_file } => lookup::run(
+            &load_index(loader, &args.index, &args.progress, &AtomicBool::default()),
+            &pubkeys_file,
+        ),
+        Action::Dump { limit } => dump::dump_accounts(&loader, &args.index.filter, limit),
+        Action::DumpAppendVec { append_vec } => dump::dump_append_vec(&loader, &append_vec),
+        Action::SlotHistogram { out } => histogram::slot_histogram(&loader, &out),
+        Action::Grep(grep_args) => grep::grep(&loader, &args.index.filter, &grep_args.pattern),
+        Action::Stats { top, json, exact_unique } => {
+            stats::AccountStats::new(&loader, &args.index.filter, top, exact_unique).print(json)
+        }
+    }
+}
 
-    let loader = UnpackedSnapshotExtractor::open(&args.source, Box::new(LoadProgressTracking {}));
+fn serve_rpc(
+    loader: UnpackedSnapshotExtractor,
+    index: &IndexArgs,
+    progress: &ProgressArgs,
+    args: RpcArgs,
+) {
+    use std::sync::atomic::Ordering;
+    use std::sync::{mpsc, Arc};
+
+    // Register SIGINT handler (before the index build so it can be interrupted).
+    let exit = Arc::new(AtomicBool::new(false));
+    let (sigint_tx, sigint_rx) = mpsc::channel();
+    ctrlc::set_handler({
+        let exit = exit.clone();
+
+        move || {
+            exit.store(true, Ordering::Relaxed);
+            let _ = sigint_tx.send(());
+        }
+    })
+    .unwrap();
+
+    // Construct the account index.
+    let rpc = load_index(loader, index, progress, &exit);
+    rpc.warmup(args.warmup_queries, args.warmup_seed);
+
+    // Bind the RPC server.
+    let server = rpc.bind(args);
+
+    // Wait for SIGINT & then shutdown the server.
+    sigint_rx.recv().unwrap();
+    server.close();
+}
+
+fn load_index(
+    loader: UnpackedSnapshotExtractor,
+    index: &IndexArgs,
+    progress: &ProgressArgs,
+    exit: &AtomicBool,
+) -> HistoricalRpc {
+    use std::time::Duration;
+
+    use indicatif::{MultiProgress, ProgressBar, ProgressDrawTarget};
+
+    use crate::utils::spawn_progress_logger;
 
     // Setup a multi progress bar & style.
     let multi = MultiProgress::new();
-    let style = ProgressStyle::with_template(
-        "{prefix:>15.bold.dim} {spinner:.green} rate={per_sec} processed={human_pos} \
-         {elapsed_precise:.cyan}",
-    )
-    .unwrap();
+    let style = progress.style();
 
     // Setup accounts processed bar.
     let accounts_bar = multi.add(ProgressBar::new_spinner());
@@ -41,21 +170,20 @@ fn main() {
     unique_accounts_bar.set_prefix("unique accounts");
     unique_accounts_bar.set_style(style);
 
-    // Construct the account index.
-    let rpc =
-        HistoricalRpc::load(loader, &accounts_bar, &unique_accounts_bar, args.transaction_rpc);
-
-    // Bind the RPC server.
-    let server = rpc.bind();
-
-    // Register SIGINT handler.
-    let (sigint_tx, sigint_rx) = mpsc::channel();
-    ctrlc::set_handler(move || {
-        let _ = sigint_tx.send(());
-    })
-    .unwrap();
+    // Replace the bars with periodic log lines if running quietly.
+    if progress.quiet() {
+        multi.set_draw_target(ProgressDrawTarget::hidden());
+        spawn_progress_logger(
+            accounts_bar.clone(),
+            unique_accounts_bar.clone(),
+            Duration::from_secs(progress.progress_interval),
+        );
+    }
 
-    // Wait for SIGINT & then shutdown the server.
-    sigint_rx.recv().unwrap();
-    server.close();
+    HistoricalRpc::load(loader, index, &accounts_bar, &unique_accounts_bar, exit).unwrap_or_else(
+        || {
+            warn!("Index build interrupted");
+            std::process::exit(130);
+        },
+    )
 }
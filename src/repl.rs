@@ -0,0 +1,93 @@
+use std::io::{BufRead, Write};
+
+use solana_account_decoder::{encode_ui_account, UiAccountEncoding};
+use solana_sdk::account::Account;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::rpc::HistoricalRpc;
+
+/// Number of data bytes shown when printing an account summary.
+const DATA_PREVIEW_LEN: usize = 64;
+
+/// Reads commands from stdin until `:quit` or EOF.
+///
+/// Supported commands:
+/// - `<pubkey>`: Print a summary of the account.
+/// - `:json <pubkey>`: Print the full account as JSON (base64 data).
+/// - `:quit`: Exit the REPL.
+pub(crate) fn run(rpc: &HistoricalRpc) {
+    let stdin = std::io::stdin();
+    let mut lines = stdin.lock().lines();
+
+    loop {
+        print!("> ");
+        std::io::stdout().flush().unwrap();
+
+        let Some(line) = lines.next() else {
+            // EOF, terminate the prompt line cleanly.
+            println!();
+            break;
+        };
+        let line = line.unwrap();
+
+        match line.split_whitespace().collect::<Vec<_>>()[..] {
+            [] => {}
+            [":quit"] => break,
+            [":json", pubkey] => {
+                let Some((pubkey, account)) = lookup(rpc, pubkey) else {
+                    continue;
+                };
+                let account =
+                    encode_ui_account(&pubkey, &account, UiAccountEncoding::Base64, None, None);
+
+                println!("{}", serde_json::to_string_pretty(&account).unwrap());
+            }
+            [pubkey] => {
+                let Some((pubkey, account)) = lookup(rpc, pubkey) else {
+                    continue;
+                };
+
+                print_summary(&pubkey, &account);
+            }
+            _ => println!("Unknown command; expected `<pubkey>`, `:json <pubkey>`, or `:quit`"),
+        }
+    }
+}
+
+fn lookup(rpc: &HistoricalRpc, pubkey: &str) -> Option<(Pubkey, Account)> {
+    let pubkey = match pubkey.parse() {
+        Ok(pubkey) => pubkey,
+        Err(err) => {
+            println!("Invalid pubkey; pubkey={pubkey}; err={err}");
+
+            return None;
+        }
+    };
+
+    match rpc.get_account(&pubkey) {
+        Some(account) => Some((pubkey, account)),
+        None => {
+            println!("Account not found; pubkey={pubkey}");
+
+            None
+        }
+    }
+}
+
+fn print_summary(pubkey: &Pubkey, account: &Account) {
+    let preview: String = account
+        .data
+        .iter()
+        .take(DATA_PREVIEW_LEN)
+        .map(|byte| format!("{byte:02x}"))
+        .collect();
+    let ellipsis = if account.data.len() > DATA_PREVIEW_LEN { "..." } else { "" };
+
+    println!("pubkey:     {pubkey}");
+    println!("lamports:   {}", account.lamports);
+    println!("owner:      {}", account.owner);
+    println!("executable: {}", account.executable);
+    println!("rent_epoch: {}", account.rent_epoch);
+    println!("data_len:   {}", account.data.len());
+    println!("data:       {preview}{ellipsis}");
+}
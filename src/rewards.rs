@@ -0,0 +1,49 @@
+use solana_sdk::account::Account;
+use solana_sdk::clock::Epoch;
+use solana_sdk::stake;
+use solana_sdk::stake::state::StakeStateV2;
+
+use crate::solana::DeserializableVersionedBank;
+
+/// Estimates the inflation reward `account` (a stake account) accrues for the
+/// snapshot's epoch. Returns `None` if `account` is not a delegated stake
+/// account.
+///
+/// This is an approximation of the runtime's reward calculation:
+/// - All of the epoch's validator inflation is assumed to be paid out (i.e.
+///   every validator earned full vote credits).
+/// - Rewards are split pro-rata by delegated stake, ignoring vote credits,
+///   warmup/cooldown & validator commission.
+/// - The inflation schedule is evaluated at the snapshot's slot.
+pub(crate) fn estimate_inflation_reward(
+    bank: &DeserializableVersionedBank,
+    account: &Account,
+) -> Option<u64> {
+    if account.owner != stake::program::id() {
+        return None;
+    }
+    let delegation = bincode::deserialize::<StakeStateV2>(&account.data)
+        .ok()?
+        .delegation()?;
+
+    // Stake that is not active for the whole epoch does not earn rewards.
+    let bootstrap = delegation.activation_epoch == Epoch::MAX;
+    if (!bootstrap && delegation.activation_epoch >= bank.epoch)
+        || delegation.deactivation_epoch <= bank.epoch
+    {
+        return Some(0);
+    }
+
+    let total_stake = bank.epoch_stakes.get(&bank.epoch)?.total_stake();
+    if total_stake == 0 {
+        return Some(0);
+    }
+
+    let year = bank.slot as f64 / bank.slots_per_year;
+    let epoch_duration_in_years =
+        bank.epoch_schedule.get_slots_in_epoch(bank.epoch) as f64 / bank.slots_per_year;
+    let validator_rewards =
+        bank.inflation.validator(year) * bank.capitalization as f64 * epoch_duration_in_years;
+
+    Some((validator_rewards * delegation.stake as f64 / total_stake as f64) as u64)
+}
@@ -1,94 +1,676 @@
-use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::cmp::Ordering as CmpOrdering;
+use std::future::Future;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
+use std::time::Instant;
 
-use hashbrown::HashMap;
+use futures::future::Either;
+use hashbrown::{HashMap, HashSet};
 use indicatif::ProgressBar;
-use jsonrpc_core::{BoxFuture, Error as JsonRpcError, MetaIoHandler, Result};
+use jsonrpc_core::middleware::{Middleware, NoopCallFuture, NoopFuture};
+use jsonrpc_core::{
+    BoxFuture, Call, Error as JsonRpcError, ErrorCode, Failure, MetaIoHandler, MethodCall, Output,
+    Result, Success,
+};
 use jsonrpc_derive::rpc;
 use jsonrpc_http_server::{
     hyper, AccessControlAllowOrigin, DomainsValidation, Server, ServerBuilder,
 };
-use solana_account_decoder::{encode_ui_account, UiAccount, UiAccountEncoding};
+#[cfg(unix)]
+use memmap2::Advice;
+use rand::rngs::StdRng;
+use rand::seq::IteratorRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use rayon::ThreadPoolBuilder;
+use solana_account_decoder::{encode_ui_account, UiAccount, UiAccountEncoding, UiDataSliceConfig};
 use solana_rpc::rpc::verify_pubkey;
 use solana_rpc_client::nonblocking::rpc_client::RpcClient;
 use solana_rpc_client_api::config::{
-    RpcAccountInfoConfig, RpcEncodingConfigWrapper, RpcTransactionConfig,
+    RpcAccountInfoConfig, RpcEncodingConfigWrapper, RpcProgramAccountsConfig,
+    RpcTokenAccountsFilter, RpcTransactionConfig,
+};
+use solana_rpc_client_api::filter::RpcFilterType;
+use solana_rpc_client_api::request::{TokenAccountsFilter, MAX_MULTIPLE_ACCOUNTS};
+use solana_rpc_client_api::response::{
+    OptionalContext, Response as RpcResponse, RpcKeyedAccount, RpcResponseContext,
 };
-use solana_rpc_client_api::response::{Response as RpcResponse, RpcResponseContext};
 use solana_sdk::account::Account;
+use solana_sdk::clock::{Slot, UnixTimestamp};
 use solana_sdk::pubkey::Pubkey;
 use solana_sdk::signature::Signature;
 use solana_transaction_status::{EncodedConfirmedTransactionWithStatusMeta, UiTransactionEncoding};
-use tracing::{debug, info};
+use tracing::{debug, info, trace, warn};
 
+use crate::append_vec::{AppendVec, StoredAccountMeta};
+use crate::args::{IndexArgs, IndexPolicy, MissingTransactionRpc, OnParseError, RpcArgs};
+use crate::error::{SnapshotError, SnapshotResult};
+use crate::index::{AccountIndex, Upsert, PACKED_MAX_ID, PACKED_MAX_SLOT};
+use crate::rewards::estimate_inflation_reward;
+use crate::token::parse_token_account;
 use crate::unpacked::UnpackedSnapshotExtractor;
-use crate::utils::append_vec_iter;
+use crate::utils::{
+    accounts_with_location, append_vec_iter, scan_append_vec, StoredAccountMetaHandle,
+};
+
+/// Assumed average stored size of an account (header & data), used to estimate
+/// the account count from the manifest. Most accounts hold little data, so this
+/// sits just above the 136 byte header.
+const ESTIMATED_STORED_ACCOUNT_SIZE: u64 = 192;
+/// Append vecs scanned per `--num-threads` thread in each index build batch.
+const SCAN_BATCH_PER_THREAD: usize = 8;
+/// Methods that can only be served if a `transaction_rpc` is configured
+/// (`getBlockTime` serves the snapshot slot without one).
+const TRANSACTION_RPC_METHODS: &[&str] = &["getBlockTime", "getTransaction"];
+/// Logged RPC params are truncated to this many characters.
+const LOG_PARAMS_MAX_LEN: usize = 128;
+/// Largest account (in bytes) that will be served with base58 encoding.
+const MAX_BASE58_BYTES: usize = 128;
+
+/// Prints the implemented JSON-RPC methods & whether they require a configured
+/// `transaction_rpc`.
+pub(crate) fn print_methods() {
+    let mut methods: Vec<_> = AccountsRpcImpl
+        .to_delegate()
+        .into_iter()
+        .map(|(name, _)| name)
+        .collect();
+    methods.sort_unstable();
+
+    for method in methods {
+        if TRANSACTION_RPC_METHODS.contains(&method.as_str()) {
+            println!("{method} (requires --transaction-rpc)");
+        } else {
+            println!("{method}");
+        }
+    }
+}
 
-const EXPECTED_ACCOUNTS: usize = 800_000_000;
-const LISTEN_ADDRESS: SocketAddr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::UNSPECIFIED, 8899));
+/// Owner (token owner or program) to the keys it (may) own. Entries are not
+/// removed when an account changes owner, so lookups must re-check the owner.
+type OwnerIndex = HashMap<Pubkey, Vec<Pubkey>>;
 
 pub(crate) struct HistoricalRpc {
     extractor: UnpackedSnapshotExtractor,
-    account_index: HashMap<Pubkey, (u64, u64)>,
+    account_index: AccountIndex,
+    /// Token owner to the token accounts it (may) own, see
+    /// [`HistoricalRpc::get_token_accounts_by_owner`].
+    token_owner_index: Option<OwnerIndex>,
+    /// Program to the accounts it (may) own, see
+    /// [`HistoricalRpc::get_program_accounts`].
+    program_owner_index: Option<OwnerIndex>,
     transaction_rpc: Option<RpcClient>,
+    missing_transaction_rpc: MissingTransactionRpc,
+    strict_lookups: bool,
+    /// Encoding used when a request does not specify one.
+    default_encoding: UiAccountEncoding,
+    /// Overrides the snapshot slot reported in response contexts.
+    context_slot: Option<u64>,
+    /// Largest `dataSlice` length served.
+    data_slice_max: usize,
 }
 
 impl HistoricalRpc {
+    /// Builds (or loads, see `--load-index`) the account index, returning
+    /// `None` if `exit` is set before the scan completes.
     pub(crate) fn load(
         extractor: UnpackedSnapshotExtractor,
+        args: &IndexArgs,
         accounts_bar: &ProgressBar,
         unique_accounts_bar: &ProgressBar,
-        transaction_rpc: Option<String>,
-    ) -> Self {
-        let transaction_rpc = transaction_rpc.map(RpcClient::new);
+        exit: &AtomicBool,
+    ) -> Option<Self> {
+        let (account_index, token_owner_index, program_owner_index) = match &args.load_index {
+            Some(path) => {
+                let account_index = AccountIndex::read_from(path, extractor.slot())
+                    .unwrap_or_else(|err| panic!("Failed to load index; path={path:?}; err={err}"));
+                info!(keys = account_index.len(), ?path, "Accounts index loaded");
+
+                (account_index, None, None)
+            }
+            None => Self::build_index(&extractor, args, accounts_bar, unique_accounts_bar, exit)?,
+        };
+        if let Some(path) = &args.save_index {
+            account_index
+                .write_to(path, extractor.slot())
+                .unwrap_or_else(|err| panic!("Failed to save index; path={path:?}; err={err}"));
+            info!(?path, "Accounts index saved");
+        }
 
-        let mut account_index = HashMap::with_capacity(EXPECTED_ACCOUNTS);
-        for append_vec in extractor.unboxed_iter() {
-            let slot = append_vec.slot();
-            let id = append_vec.id();
+        Some(HistoricalRpc {
+            extractor,
+            account_index,
+            token_owner_index,
+            program_owner_index,
+            transaction_rpc: None,
+            missing_transaction_rpc: MissingTransactionRpc::default(),
+            strict_lookups: false,
+            default_encoding: UiAccountEncoding::Base64,
+            context_slot: None,
+            data_slice_max: usize::MAX,
+        })
+    }
 
-            for account in append_vec_iter(&append_vec) {
-                accounts_bar.inc(1);
+    /// Scans every append vec to build the account index (& token owner index
+    /// if enabled).
+    fn build_index(
+        extractor: &UnpackedSnapshotExtractor,
+        args: &IndexArgs,
+        accounts_bar: &ProgressBar,
+        unique_accounts_bar: &ProgressBar,
+        exit: &AtomicBool,
+    ) -> Option<(AccountIndex, Option<OwnerIndex>, Option<OwnerIndex>)> {
+        if args.packed_index {
+            for (slot, entries) in &extractor.accounts_db_fields().0 {
+                for entry in entries {
+                    assert!(
+                        *slot <= PACKED_MAX_SLOT && entry.id as u64 <= PACKED_MAX_ID,
+                        "Append vec does not fit packed index; slot={slot}; id={}",
+                        entry.id
+                    );
+                }
+            }
+        }
 
-                let account = account.access().unwrap();
-                let key = account.meta.pubkey;
+        let capacity = args
+            .expected_accounts
+            .or_else(|| {
+                args.account_capacity_hint
+                    .as_deref()
+                    .and_then(|path| std::fs::read_to_string(path).ok())
+                    .and_then(|hint| hint.trim().parse().ok())
+            })
+            .unwrap_or_else(|| estimate_accounts(extractor));
+        debug!(capacity, "Sizing account index");
+        let mut account_index = AccountIndex::with_capacity(args.packed_index, capacity);
+        let mut token_owner_index = args.token_owner_index.then(HashMap::new);
+        let mut program_owner_index = args.program_owner_index.then(HashMap::new);
 
-                // Insert the slot if it's newer.
-                let entry = account_index.entry(key).or_insert_with(|| {
-                    unique_accounts_bar.inc(1);
+        // Write versions of the accounts indexed from the current slot. Append vecs
+        // are yielded in slot order, so this only needs to span a single slot.
+        let mut slot_write_versions = HashMap::new();
+        let mut current_slot = None;
+        let mut shared_slot = false;
+        // How the indexed location compares to a location that replaces it.
+        let replaced_ordering = match args.index_policy {
+            IndexPolicy::Newest => CmpOrdering::Less,
+            IndexPolicy::Oldest => CmpOrdering::Greater,
+        };
+        // End offset of the last account parsed from each append vec.
+        let mut parsed_ends = HashMap::new();
+        let mut hash_mismatches = 0;
+        // Keys whose indexed version fails the account filter. The filter is applied
+        // to the resolved version (not each stored version), so an account is never
+        // served from an older version that happened to pass it.
+        let mut excluded = HashSet::new();
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(args.num_threads)
+            .build()
+            .unwrap();
+        let mut append_vecs = extractor.unboxed_iter();
+        loop {
+            let batch: Vec<_> = append_vecs
+                .by_ref()
+                .take(pool.current_num_threads() * SCAN_BATCH_PER_THREAD)
+                .collect();
+            if batch.is_empty() {
+                break;
+            }
 
-                    (slot, id)
-                });
-                if entry.0 < slot {
-                    *entry = (slot, id);
+            // NB: Batches are scanned in parallel but merged in `(slot, id)` order, so the
+            // index does not depend on thread scheduling.
+            let scanned: Vec<_> = pool.install(|| {
+                batch
+                    .par_iter()
+                    .map(|append_vec| {
+                        scan_for_index(extractor, append_vec, args, accounts_bar, exit)
+                    })
+                    .collect()
+            });
+            for scanned in scanned {
+                let ScannedAppendVec { slot, id, end, accounts, hash_mismatches: mismatches } =
+                    scanned?;
+                hash_mismatches += mismatches;
+                if let Some(end) = end {
+                    parsed_ends.insert((slot, id), end);
+                }
+
+                if current_slot != Some(slot) {
+                    current_slot = Some(slot);
+                    slot_write_versions.clear();
+                    shared_slot = extractor
+                        .accounts_db_fields()
+                        .0
+                        .get(&slot)
+                        .is_some_and(|entries| entries.len() > 1);
+                }
+
+                for ScannedAccount { key, offset, write_version, owner, token_owner, matches } in
+                    accounts
+                {
+                    // Insert the location if it's newer (older under `IndexPolicy::Oldest`),
+                    // falling back to the write version if the account is stored in multiple
+                    // append vecs of the same slot, & then to the lower append vec id (under
+                    // either policy) so ties never depend on the merge order.
+                    let upsert = account_index.upsert(
+                        key,
+                        (slot, id, offset),
+                        |indexed_slot, indexed_id| match indexed_slot.cmp(&slot) {
+                            CmpOrdering::Equal => match slot_write_versions
+                                .get(&key)
+                                .map(|indexed| indexed.cmp(&write_version))
+                            {
+                                Some(CmpOrdering::Equal) => id < indexed_id,
+                                Some(ordering) => ordering == replaced_ordering,
+                                None => false,
+                            },
+                            ordering => ordering == replaced_ordering,
+                        },
+                    );
+                    trace!(
+                        target: "snapshot_etl::scan",
+                        %key,
+                        slot,
+                        id,
+                        write_version,
+                        ?upsert,
+                        "Indexed account"
+                    );
+                    if upsert == Upsert::Inserted {
+                        unique_accounts_bar.inc(1);
+                    }
+                    if upsert == Upsert::Retained {
+                        continue;
+                    }
+                    if shared_slot {
+                        slot_write_versions.insert(key, write_version);
+                    }
+                    if !matches {
+                        excluded.insert(key);
+
+                        continue;
+                    }
+                    excluded.remove(&key);
+                    if let (Some(token_owner_index), Some(token_owner)) =
+                        (&mut token_owner_index, token_owner)
+                    {
+                        token_owner_index.entry(token_owner).or_default().push(key);
+                    }
+                    if let Some(program_owner_index) = &mut program_owner_index {
+                        program_owner_index.entry(owner).or_default().push(key);
+                    }
                 }
             }
         }
 
-        info!(keys = account_index.len(), "Accounts index constructed");
+        let filtered = excluded.len();
+        for key in &excluded {
+            account_index.remove(key);
+        }
+        unique_accounts_bar.set_position(account_index.len() as u64);
+        info!(keys = account_index.len(), filtered, "Accounts index constructed");
+        if args.verify_hashes {
+            assert_eq!(hash_mismatches, 0, "Account hash mismatches; mismatches={hash_mismatches}");
+            info!("Verified account hashes");
+        }
+        extractor.log_scan_errors();
+        let mut skipped = extractor.skipped();
+        // NB: Truncated append vecs are still verified so the missing bytes are
+        // reported.
+        if extractor.on_parse_error() == OnParseError::Skip {
+            skipped.extend(extractor.corrupt());
+        }
+        verify_parsed_bytes(extractor, &parsed_ends, &skipped, args.strict_scan);
+        if let Some(path) = &args.account_capacity_hint {
+            if let Err(err) = std::fs::write(path, account_index.len().to_string()) {
+                warn!(?path, %err, "Failed to write account capacity hint");
+            }
+        }
         accounts_bar.finish();
         unique_accounts_bar.finish();
 
-        HistoricalRpc { extractor, account_index, transaction_rpc }
+        Some((account_index, token_owner_index, program_owner_index))
     }
 
-    pub(crate) const fn slot(&self) -> u64 {
-        self.extractor.slot()
+    /// The slot reported in response contexts (the snapshot slot unless
+    /// overridden by `--context-slot`).
+    pub(crate) fn slot(&self) -> u64 {
+        self.context_slot.unwrap_or_else(|| self.extractor.slot())
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.account_index.len()
+    }
+
+    /// Returns the `(slot, id)` of the append vec holding the newest version of
+    /// `key`.
+    pub(crate) fn location(&self, key: &Pubkey) -> Option<(u64, u64)> {
+        self.account_index.get(key)
+    }
+
+    /// Yields the newest version of every indexed account alongside the
+    /// `(slot, id)` of the append vec storing it.
+    pub(crate) fn newest_accounts(
+        &self,
+    ) -> impl Iterator<Item = (u64, u64, StoredAccountMetaHandle<Rc<AppendVec>>)> + '_ {
+        accounts_with_location(&self.extractor).filter(|(slot, id, account)| {
+            let account = account.access().unwrap();
+
+            self.location(&account.meta.pubkey) == Some((*slot, *id))
+        })
+    }
+
+    /// Times [`HistoricalRpc::try_get_account`] for `queries` keys sampled
+    /// uniformly (seeded by `seed`) from the index & logs the latency
+    /// percentiles & the share of lookups that returned the account.
+    pub(crate) fn warmup(&self, queries: usize, seed: u64) {
+        if queries == 0 || self.len() == 0 {
+            return;
+        }
+
+        let keys = self
+            .account_index
+            .keys()
+            .choose_multiple(&mut StdRng::seed_from_u64(seed), queries);
+        let mut hits = 0;
+        let mut latencies: Vec<_> = keys
+            .into_iter()
+            .map(|key| {
+                let start = Instant::now();
+                if let Ok(Some(_)) = self.try_get_account(key) {
+                    hits += 1;
+                }
+
+                start.elapsed()
+            })
+            .collect();
+        latencies.sort_unstable();
+
+        let percentile = |percentile: usize| latencies[(latencies.len() - 1) * percentile / 100];
+        info!(
+            queries = latencies.len(),
+            hits,
+            misses = latencies.len() - hits,
+            hit_rate = hits as f64 / latencies.len() as f64,
+            p50 = ?percentile(50),
+            p95 = ?percentile(95),
+            p99 = ?percentile(99),
+            max = ?percentile(100),
+            "Warmup queries complete"
+        );
     }
 
+    /// Returns the newest version of `key`, logging & returning `None` if the
+    /// account is indexed but could not be read.
     pub(crate) fn get_account(&self, key: &Pubkey) -> Option<Account> {
-        let (slot, id) = *self.account_index.get(key)?;
+        self.try_get_account(key).unwrap_or_else(|err| {
+            warn!(%err, "Failed to read indexed account");
 
-        let path = self.extractor.root().join(format!("accounts/{slot}.{id}"));
-        let vec = self.extractor.open_append_vec(slot, id, &path);
-        let account = append_vec_iter(&vec)
-            .find(|account| &account.access().unwrap().meta.pubkey == key)
-            .unwrap()
-            .access()
-            .unwrap()
-            .clone_account();
+            None
+        })
+    }
+
+    /// Returns the newest version of `key`, or `Ok(None)` if the key is not in
+    /// the index. Errors if the key is indexed but its append vec could not be
+    /// opened or does not contain the account.
+    pub(crate) fn try_get_account(&self, key: &Pubkey) -> SnapshotResult<Option<Account>> {
+        self.read_account(key, |account| account.clone_account())
+    }
+
+    /// Like [`HistoricalRpc::try_get_account`] but returns the account with
+    /// empty data (the data is never read from the append vec) alongside its
+    /// stored data length.
+    pub(crate) fn try_get_account_meta(
+        &self,
+        key: &Pubkey,
+    ) -> SnapshotResult<Option<(Account, usize)>> {
+        self.read_account(key, |account| {
+            let meta = Account {
+                lamports: account.account_meta.lamports,
+                data: Vec::new(),
+                owner: account.account_meta.owner,
+                executable: account.account_meta.executable,
+                rent_epoch: account.account_meta.rent_epoch,
+            };
+
+            (meta, account.data.len())
+        })
+    }
+
+    /// Looks up `key` for an RPC response, returning the account & its stored
+    /// data length. A zero length `data_slice` skips reading the account's
+    /// data. Read failures are errors under `--strict-lookups` & otherwise
+    /// logged & treated as missing accounts.
+    fn lookup_account(
+        &self,
+        key: &Pubkey,
+        data_slice: Option<UiDataSliceConfig>,
+    ) -> Result<Option<(Account, usize)>> {
+        let account = match data_slice {
+            Some(UiDataSliceConfig { length: 0, .. }) => self.try_get_account_meta(key),
+            _ => self.try_get_account(key).map(|account| {
+                account.map(|account| {
+                    let data_len = account.data.len();
+
+                    (account, data_len)
+                })
+            }),
+        };
+
+        match account {
+            Ok(account) => Ok(account),
+            Err(err) if self.strict_lookups => Err(JsonRpcError {
+                code: ErrorCode::InternalError,
+                message: err.to_string(),
+                data: None,
+            }),
+            Err(err) => {
+                warn!(%err, "Failed to read indexed account");
+
+                Ok(None)
+            }
+        }
+    }
+
+    fn read_account<T>(
+        &self,
+        key: &Pubkey,
+        read: impl FnOnce(&StoredAccountMeta) -> T,
+    ) -> SnapshotResult<Option<T>> {
+        let Some((slot, id, offset)) = self.account_index.get_with_offset(key) else {
+            return Ok(None);
+        };
+
+        let path = self.extractor.append_vec_path(slot, id);
+        let vec = self.extractor.open_append_vec(slot, id, &path)?;
+        #[cfg(unix)]
+        if offset.is_some() {
+            if let Err(err) = vec.advise(Advice::Random) {
+                debug!(slot, id, %err, "Failed to advise mmap");
+            }
+        }
+        let account = read_indexed_account(&vec, key, offset, read)
+            .ok_or(SnapshotError::AccountMissing { slot, id, key: *key })?;
+
+        Ok(Some(account))
+    }
+
+    /// Returns the newest version of each of `keys` (in input order), opening
+    /// each append vec holding any of the keys once. Each key is read at its
+    /// indexed offset (as [`HistoricalRpc::get_account`] does), the packed
+    /// index (which stores no offsets) scans each append vec once instead.
+    pub(crate) fn get_accounts(&self, keys: &[Pubkey]) -> Vec<Option<Account>> {
+        // Bucket the requested keys by the append vec holding them.
+        let mut append_vecs: HashMap<(u64, u64), Vec<(usize, Pubkey, Option<usize>)>> =
+            HashMap::new();
+        for (i, key) in keys.iter().enumerate() {
+            if let Some((slot, id, offset)) = self.account_index.get_with_offset(key) {
+                append_vecs
+                    .entry((slot, id))
+                    .or_default()
+                    .push((i, *key, offset));
+            }
+        }
+
+        let mut accounts = vec![None; keys.len()];
+        for ((slot, id), requested) in append_vecs {
+            let path = self.extractor.append_vec_path(slot, id);
+            let vec = match self.extractor.open_append_vec(slot, id, &path) {
+                Ok(vec) => vec,
+                Err(err) => {
+                    warn!(%err, "Failed to read indexed accounts");
+
+                    continue;
+                }
+            };
+
+            let mut found = 0;
+            if requested.iter().all(|(_, _, offset)| offset.is_some()) {
+                #[cfg(unix)]
+                if let Err(err) = vec.advise(Advice::Random) {
+                    debug!(slot, id, %err, "Failed to advise mmap");
+                }
+                for &(i, key, offset) in &requested {
+                    accounts[i] =
+                        read_indexed_account(&vec, &key, offset, |account| account.clone_account());
+                    found += usize::from(accounts[i].is_some());
+                }
+            } else {
+                // NB: Scan the append vec once, taking the first version of each key (as
+                // `read_indexed_account` does without an offset).
+                let mut pending: HashMap<Pubkey, Vec<usize>> = HashMap::new();
+                for &(i, key, _) in &requested {
+                    pending.entry(key).or_default().push(i);
+                }
+                for account in append_vec_iter(&vec) {
+                    let account = account.access().unwrap();
+                    let Some(indices) = pending.remove(&account.meta.pubkey) else {
+                        continue;
+                    };
+
+                    let account = account.clone_account();
+                    found += indices.len();
+                    for i in indices {
+                        accounts[i] = Some(account.clone());
+                    }
+                    if pending.is_empty() {
+                        break;
+                    }
+                }
+            }
+            if found < requested.len() {
+                warn!(
+                    slot,
+                    id,
+                    missing = requested.len() - found,
+                    "Indexed accounts missing from append vec"
+                );
+            }
+        }
+
+        accounts
+    }
+
+    /// Returns the token accounts owned by `owner` matching `filter`. Returns
+    /// `None` if the token owner index was not built.
+    pub(crate) fn get_token_accounts_by_owner(
+        &self,
+        owner: &Pubkey,
+        filter: &TokenAccountsFilter,
+    ) -> Option<Vec<(Pubkey, Account)>> {
+        let mut keys = self
+            .token_owner_index
+            .as_ref()?
+            .get(owner)
+            .cloned()
+            .unwrap_or_default();
+        keys.sort_unstable();
+        keys.dedup();
+
+        // NB: The index may contain stale entries (the key was later transferred to a
+        // new owner), so re-check the owner against the newest version of the account.
+        let accounts = self.get_accounts(&keys);
+        let accounts = keys
+            .into_iter()
+            .zip(accounts)
+            .filter_map(|(key, account)| Some((key, account?)))
+            .filter(|(_, account)| {
+                let Some((mint, token_owner)) = parse_token_account(&account.owner, &account.data)
+                else {
+                    return false;
+                };
+
+                token_owner == *owner
+                    && match filter {
+                        TokenAccountsFilter::Mint(filter) => mint == *filter,
+                        TokenAccountsFilter::ProgramId(filter) => account.owner == *filter,
+                    }
+            })
+            .collect();
+
+        Some(accounts)
+    }
+
+    /// Returns the accounts owned by `program` matching every filter. Returns
+    /// `None` if the program owner index was not built.
+    pub(crate) fn get_program_accounts(
+        &self,
+        program: &Pubkey,
+        filters: &[RpcFilterType],
+    ) -> Option<Vec<(Pubkey, Account)>> {
+        let mut keys = self
+            .program_owner_index
+            .as_ref()?
+            .get(program)
+            .cloned()
+            .unwrap_or_default();
+        keys.sort_unstable();
+        keys.dedup();
 
-        Some(account)
+        // NB: The index may contain stale entries (the account was later assigned to a
+        // new program), so re-check the owner against the newest version of the
+        // account.
+        let accounts = self.get_accounts(&keys);
+        let accounts = keys
+            .into_iter()
+            .zip(accounts)
+            .filter_map(|(key, account)| Some((key, account?)))
+            .filter(|(_, account)| {
+                account.owner == *program
+                    && filters.iter().all(|filter| match filter {
+                        RpcFilterType::DataSize(size) => account.data.len() as u64 == *size,
+                        RpcFilterType::Memcmp(memcmp) => memcmp.bytes_match(&account.data),
+                        // NB: Rejected by `verify_program_filters`.
+                        RpcFilterType::TokenAccountState => false,
+                    })
+            })
+            .collect();
+
+        Some(accounts)
+    }
+
+    /// Approximates the snapshot slot's block time as `genesis_creation_time +
+    /// slot * ns_per_slot` (i.e. assuming every slot since genesis took the
+    /// target slot time).
+    fn snapshot_block_time(&self) -> UnixTimestamp {
+        let bank = self.extractor.bank();
+        let elapsed_secs = u128::from(bank.slot) * bank.ns_per_slot / 1_000_000_000;
+
+        bank.genesis_creation_time + elapsed_secs as UnixTimestamp
+    }
+
+    async fn get_block_time(&self, slot: Slot) -> Result<Option<UnixTimestamp>> {
+        if slot == self.extractor.slot() {
+            return Ok(Some(self.snapshot_block_time()));
+        }
+        let Some(rpc) = &self.transaction_rpc else {
+            return Ok(None);
+        };
+
+        rpc.get_block_time(slot).await.map(Some).map_err(|err| {
+            JsonRpcError::invalid_params(format!("transaction_rpc failed; err={err:?}"))
+        })
     }
 
     async fn get_transaction(
@@ -97,9 +679,13 @@ impl HistoricalRpc {
         config: Option<RpcEncodingConfigWrapper<RpcTransactionConfig>>,
     ) -> Result<Option<EncodedConfirmedTransactionWithStatusMeta>> {
         let Some(rpc) = &self.transaction_rpc else {
-            return Err(JsonRpcError::invalid_params(
-                "This historical RPC does not have a provided transaction_rpc".to_string(),
-            ));
+            return match self.missing_transaction_rpc {
+                MissingTransactionRpc::InvalidParams => Err(JsonRpcError::invalid_params(
+                    "This historical RPC does not have a provided transaction_rpc".to_string(),
+                )),
+                MissingTransactionRpc::MethodNotFound => Err(JsonRpcError::method_not_found()),
+                MissingTransactionRpc::Null => Ok(None),
+            };
         };
 
         let config = config
@@ -121,21 +707,47 @@ impl HistoricalRpc {
             })
     }
 
-    pub(crate) fn bind(self) -> Server {
+    pub(crate) fn bind(mut self, args: RpcArgs) -> Server {
+        self.transaction_rpc = args.transaction_rpc.map(RpcClient::new);
+        self.missing_transaction_rpc = args.missing_transaction_rpc;
+        self.strict_lookups = args.strict_lookups;
+        self.default_encoding = args.default_encoding.into();
+        self.context_slot = args.context_slot;
+        self.data_slice_max = args.data_slice_max;
+        if self.slot() == 0 {
+            warn!(
+                "Serving context slot 0, clients using minContextSlot may misbehave; see \
+                 --context-slot"
+            );
+        }
         let historical_rpc = Arc::new(self);
 
         // Bind the RPC server.
-        let mut io = MetaIoHandler::default();
-        io.extend_with(AccountsRpcImpl.to_delegate());
+        let mut io = MetaIoHandler::with_middleware(RequestLogger::new(args.rpc_log_sample));
+        let methods: Vec<_> = AccountsRpcImpl.to_delegate().into_iter().collect();
+        for method in args.enable_method.iter().chain(&args.disable_method) {
+            assert!(
+                methods.iter().any(|(name, _)| name == method),
+                "Unknown RPC method; method={method}; see --list-methods"
+            );
+        }
+        io.extend_with(methods.into_iter().filter(|(name, _)| {
+            (args.enable_method.is_empty() || args.enable_method.contains(name))
+                && !args.disable_method.contains(name)
+        }));
 
         ServerBuilder::with_meta_extractor(io, move |_: &hyper::Request<hyper::Body>| {
             historical_rpc.clone()
         })
         .threads(1)
+        .keep_alive(args.keep_alive)
+        .max_request_body_size(args.max_request_body)
         .cors(DomainsValidation::AllowOnly(vec![AccessControlAllowOrigin::Any]))
         .cors_max_age(86400)
-        .start_http(&LISTEN_ADDRESS)
-        .unwrap()
+        .start_http(&args.listen_addr)
+        .unwrap_or_else(|err| {
+            panic!("Failed to bind RPC server; listen_addr={}; err={err}", args.listen_addr)
+        })
     }
 }
 
@@ -151,6 +763,45 @@ pub trait AccountsRpc {
         config: Option<RpcAccountInfoConfig>,
     ) -> Result<RpcResponse<Option<UiAccount>>>;
 
+    #[rpc(meta, name = "getMultipleAccounts")]
+    fn get_multiple_accounts(
+        &self,
+        meta: Self::Metadata,
+        pubkey_strs: Vec<String>,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> Result<RpcResponse<Vec<Option<UiAccount>>>>;
+
+    #[rpc(meta, name = "getProgramAccounts")]
+    fn get_program_accounts(
+        &self,
+        meta: Self::Metadata,
+        program_id_str: String,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> Result<OptionalContext<Vec<RpcKeyedAccount>>>;
+
+    #[rpc(meta, name = "getTokenAccountsByOwner")]
+    fn get_token_accounts_by_owner(
+        &self,
+        meta: Self::Metadata,
+        owner_str: String,
+        token_account_filter: RpcTokenAccountsFilter,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> Result<RpcResponse<Vec<RpcKeyedAccount>>>;
+
+    #[rpc(meta, name = "getEstimatedInflationReward")]
+    fn get_estimated_inflation_reward(
+        &self,
+        meta: Self::Metadata,
+        pubkey_str: String,
+    ) -> Result<RpcResponse<Option<u64>>>;
+
+    #[rpc(meta, name = "getBlockTime")]
+    fn get_block_time(
+        &self,
+        meta: Self::Metadata,
+        slot: Slot,
+    ) -> BoxFuture<Result<Option<UnixTimestamp>>>;
+
     #[rpc(meta, name = "getTransaction")]
     fn get_transaction(
         &self,
@@ -176,31 +827,175 @@ impl AccountsRpc for AccountsRpcImpl {
         let slot = meta.slot();
 
         // Validate arguments.
-        let RpcAccountInfoConfig { encoding, data_slice, min_context_slot, .. } =
-            config.unwrap_or_default();
-        let min_context_slot = min_context_slot.unwrap_or(0);
-        if encoding != Some(UiAccountEncoding::Base64) {
-            return Err(JsonRpcError::invalid_params(format!(
-                "Expected base64 encoding; received={encoding:?}"
-            )));
-        }
-        if data_slice.is_some() {
+        let (encoding, data_slice) = verify_account_config(&meta, config)?;
+
+        // Load the account (a zero length data slice only needs the account's meta).
+        let account = meta
+            .lookup_account(&pubkey, data_slice)?
+            .map(|(account, data_len)| {
+                encode_account(&pubkey, &account, data_len, encoding, data_slice)
+            })
+            .transpose()?;
+
+        Ok(RpcResponse { context: RpcResponseContext::new(slot), value: account })
+    }
+
+    fn get_multiple_accounts(
+        &self,
+        meta: Self::Metadata,
+        pubkeys: Vec<String>,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> Result<RpcResponse<Vec<Option<UiAccount>>>> {
+        debug!(keys = pubkeys.len(), "get_multiple_accounts rpc request received");
+        if pubkeys.len() > MAX_MULTIPLE_ACCOUNTS {
             return Err(JsonRpcError::invalid_params(format!(
-                "Account data_slice unsupported; received={data_slice:?}"
+                "Too many inputs provided; max {MAX_MULTIPLE_ACCOUNTS}"
             )));
         }
-        if min_context_slot > meta.slot() {
-            return Err(JsonRpcError::invalid_params(format!(
-                "Min context slot not reached; requested={min_context_slot}; highest={slot}",
-            )));
+        let pubkeys = pubkeys
+            .iter()
+            .map(|pubkey| verify_pubkey(pubkey))
+            .collect::<Result<Vec<_>>>()?;
+        let slot = meta.slot();
+
+        // Validate arguments.
+        let (encoding, data_slice) = verify_account_config(&meta, config)?;
+
+        // Load the accounts (in input order).
+        let accounts = pubkeys
+            .iter()
+            .map(|pubkey| {
+                meta.lookup_account(pubkey, data_slice)?
+                    .map(|(account, data_len)| {
+                        encode_account(pubkey, &account, data_len, encoding, data_slice)
+                    })
+                    .transpose()
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(RpcResponse { context: RpcResponseContext::new(slot), value: accounts })
+    }
+
+    fn get_program_accounts(
+        &self,
+        meta: Self::Metadata,
+        program_id: String,
+        config: Option<RpcProgramAccountsConfig>,
+    ) -> Result<OptionalContext<Vec<RpcKeyedAccount>>> {
+        debug!(program_id, "get_program_accounts rpc request received");
+        let program_id = verify_pubkey(&program_id)?;
+        let slot = meta.slot();
+
+        // Validate arguments.
+        let RpcProgramAccountsConfig { filters, account_config, with_context, .. } =
+            config.unwrap_or_default();
+        let filters = filters.unwrap_or_default();
+        verify_program_filters(&filters)?;
+        let (encoding, data_slice) = verify_account_config(&meta, Some(account_config))?;
+
+        // Load the accounts.
+        let accounts = meta
+            .get_program_accounts(&program_id, &filters)
+            .ok_or_else(|| {
+                JsonRpcError::invalid_params(
+                    "Program owner index disabled; see --program-owner-index",
+                )
+            })?;
+        let accounts = accounts
+            .into_iter()
+            .map(|(pubkey, account)| {
+                Ok(RpcKeyedAccount {
+                    pubkey: pubkey.to_string(),
+                    account: encode_account(
+                        &pubkey,
+                        &account,
+                        account.data.len(),
+                        encoding,
+                        data_slice,
+                    )?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        if with_context.unwrap_or_default() {
+            Ok(OptionalContext::Context(RpcResponse {
+                context: RpcResponseContext::new(slot),
+                value: accounts,
+            }))
+        } else {
+            Ok(OptionalContext::NoContext(accounts))
         }
+    }
 
-        // Load the account.
-        let account = meta.get_account(&pubkey).map(|account| {
-            encode_ui_account(&pubkey, &account, UiAccountEncoding::Base64, None, None)
-        });
+    fn get_token_accounts_by_owner(
+        &self,
+        meta: Self::Metadata,
+        owner: String,
+        token_account_filter: RpcTokenAccountsFilter,
+        config: Option<RpcAccountInfoConfig>,
+    ) -> Result<RpcResponse<Vec<RpcKeyedAccount>>> {
+        debug!(owner, "get_token_accounts_by_owner rpc request received");
+        let owner = verify_pubkey(&owner)?;
+        let slot = meta.slot();
 
-        Ok(RpcResponse { context: RpcResponseContext::new(slot), value: account })
+        // Validate arguments.
+        let (encoding, data_slice) = verify_account_config(&meta, config)?;
+        let filter = match token_account_filter {
+            RpcTokenAccountsFilter::Mint(mint) => TokenAccountsFilter::Mint(verify_pubkey(&mint)?),
+            RpcTokenAccountsFilter::ProgramId(program) => {
+                TokenAccountsFilter::ProgramId(verify_pubkey(&program)?)
+            }
+        };
+
+        // Load the accounts.
+        let accounts = meta
+            .get_token_accounts_by_owner(&owner, &filter)
+            .ok_or_else(|| {
+                JsonRpcError::invalid_params("Token owner index disabled; see --token-owner-index")
+            })?;
+        let accounts = accounts
+            .into_iter()
+            .map(|(pubkey, account)| {
+                Ok(RpcKeyedAccount {
+                    pubkey: pubkey.to_string(),
+                    account: encode_account(
+                        &pubkey,
+                        &account,
+                        account.data.len(),
+                        encoding,
+                        data_slice,
+                    )?,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(RpcResponse { context: RpcResponseContext::new(slot), value: accounts })
+    }
+
+    fn get_estimated_inflation_reward(
+        &self,
+        meta: Self::Metadata,
+        pubkey: String,
+    ) -> Result<RpcResponse<Option<u64>>> {
+        debug!(pubkey, "get_estimated_inflation_reward rpc request received");
+        let pubkey = verify_pubkey(&pubkey)?;
+        let slot = meta.slot();
+
+        let reward = meta
+            .get_account(&pubkey)
+            .and_then(|account| estimate_inflation_reward(meta.extractor.bank(), &account));
+
+        Ok(RpcResponse { context: RpcResponseContext::new(slot), value: reward })
+    }
+
+    fn get_block_time(
+        &self,
+        meta: Self::Metadata,
+        slot: Slot,
+    ) -> BoxFuture<Result<Option<UnixTimestamp>>> {
+        debug!(slot, "get_block_time rpc request received");
+
+        Box::pin(async move { meta.get_block_time(slot).await })
     }
 
     fn get_transaction(
@@ -218,3 +1013,895 @@ impl AccountsRpc for AccountsRpcImpl {
         }
     }
 }
+
+/// An account parsed by [`scan_for_index`].
+struct ScannedAccount {
+    key: Pubkey,
+    offset: usize,
+    write_version: u64,
+    owner: Pubkey,
+    /// Set if the token owner index is enabled & the account is a token
+    /// account.
+    token_owner: Option<Pubkey>,
+    /// Whether this version passes the account filter.
+    matches: bool,
+}
+
+/// Reads `key` from `vec` at `offset`, or (without an offset, as the packed
+/// index stores none) from its first version in `vec`. Returns `None` if the
+/// account is not found.
+fn read_indexed_account<T>(
+    vec: &AppendVec,
+    key: &Pubkey,
+    offset: Option<usize>,
+    read: impl FnOnce(&StoredAccountMeta) -> T,
+) -> Option<T> {
+    match offset {
+        Some(offset) => vec
+            .get_account(offset)
+            .filter(|(account, _)| &account.meta.pubkey == key)
+            .map(|(account, _)| read(&account)),
+        None => append_vec_iter(vec)
+            .find(|account| {
+                account
+                    .access()
+                    .is_some_and(|account| &account.meta.pubkey == key)
+            })
+            .and_then(|account| account.access().map(|account| read(&account))),
+    }
+}
+
+/// Estimates the number of stored accounts from the manifest's account bytes.
+/// Counts every stored version, so over-estimates snapshots with many
+/// versions per account.
+fn estimate_accounts(extractor: &UnpackedSnapshotExtractor) -> usize {
+    let stored_bytes: u64 = extractor
+        .accounts_db_fields()
+        .0
+        .values()
+        .flatten()
+        .map(|entry| entry.accounts_current_len as u64)
+        .sum();
+
+    usize::try_from(stored_bytes / ESTIMATED_STORED_ACCOUNT_SIZE).unwrap_or(usize::MAX)
+}
+
+/// The accounts parsed from a single append vec by [`scan_for_index`].
+struct ScannedAppendVec {
+    slot: u64,
+    id: u64,
+    /// End offset of the last account parsed (including filtered accounts).
+    end: Option<usize>,
+    /// Every parsed account, in storage order.
+    accounts: Vec<ScannedAccount>,
+    /// Accounts whose stored hash does not match their contents (only checked
+    /// under `--verify-hashes`).
+    hash_mismatches: u64,
+}
+
+/// Parses the accounts of `append_vec` to be merged into the index, returning
+/// `None` if `exit` is set before the scan completes.
+fn scan_for_index(
+    extractor: &UnpackedSnapshotExtractor,
+    append_vec: &AppendVec,
+    args: &IndexArgs,
+    accounts_bar: &ProgressBar,
+    exit: &AtomicBool,
+) -> Option<ScannedAppendVec> {
+    let mut scanned = ScannedAppendVec {
+        slot: append_vec.slot(),
+        id: append_vec.id(),
+        end: None,
+        accounts: Vec::new(),
+        hash_mismatches: 0,
+    };
+    // NB: Each append vec is read front to back exactly once.
+    #[cfg(unix)]
+    for advice in [Advice::Sequential, Advice::WillNeed] {
+        if let Err(err) = append_vec.advise(advice) {
+            debug!(slot = scanned.slot, id = scanned.id, ?advice, %err, "Failed to advise mmap");
+        }
+    }
+    for account in scan_append_vec(extractor, append_vec) {
+        if exit.load(Ordering::Relaxed) {
+            return None;
+        }
+        accounts_bar.inc(1);
+
+        let account = account.access().unwrap();
+        scanned.end = Some(account.offset + account.stored_size);
+        if args.verify_hashes && !account.verify_hash() {
+            warn!(
+                slot = scanned.slot,
+                id = scanned.id,
+                key = %account.meta.pubkey,
+                stored = %account.hash,
+                "Account hash mismatch"
+            );
+            scanned.hash_mismatches += 1;
+        }
+        let token_owner = args
+            .token_owner_index
+            .then(|| parse_token_account(&account.account_meta.owner, account.data))
+            .flatten()
+            .map(|(_, token_owner)| token_owner);
+        scanned.accounts.push(ScannedAccount {
+            key: account.meta.pubkey,
+            offset: account.offset,
+            write_version: account.meta.write_version_obsolete,
+            owner: account.account_meta.owner,
+            token_owner,
+            matches: args.filter.matches(&account),
+        });
+    }
+
+    Some(scanned)
+}
+
+/// Confirms the bytes parsed from each append vec sum to the manifest's total
+/// `accounts_current_len` (excluding skipped append vecs). A mismatch means
+/// append vecs are missing, truncated or the parser stopped early.
+fn verify_parsed_bytes(
+    extractor: &UnpackedSnapshotExtractor,
+    parsed_ends: &HashMap<(u64, u64), usize>,
+    skipped: &[(u64, u64)],
+    strict: bool,
+) {
+    let mut expected = 0u64;
+    let mut parsed = 0u64;
+    for (slot, entries) in &extractor.accounts_db_fields().0 {
+        for entry in entries {
+            let location = (*slot, entry.id as u64);
+            if skipped.contains(&location) {
+                continue;
+            }
+
+            // NB: The final account's end is u64 aligned, so it may exceed the current len.
+            let end = parsed_ends.get(&location).copied().unwrap_or(0);
+            expected += entry.accounts_current_len as u64;
+            parsed += end.min(entry.accounts_current_len) as u64;
+        }
+    }
+
+    if parsed != expected {
+        let delta = expected - parsed;
+        assert!(
+            !strict,
+            "Parsed bytes do not match manifest; expected={expected}; parsed={parsed}; \
+             delta={delta}"
+        );
+        warn!(expected, parsed, delta, "Parsed bytes do not match manifest");
+    }
+}
+
+/// Logs (via `tracing`) a sampled fraction of RPC calls & every failed call.
+struct RequestLogger {
+    /// Log every `sample_interval`th call; `None` disables logging.
+    sample_interval: Option<u64>,
+    calls: AtomicU64,
+}
+
+impl RequestLogger {
+    fn new(sample_rate: Option<f64>) -> Self {
+        RequestLogger {
+            sample_interval: sample_rate.map(|rate| (1.0 / rate).round() as u64),
+            calls: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Middleware<Arc<HistoricalRpc>> for RequestLogger {
+    type Future = NoopFuture;
+    type CallFuture = NoopCallFuture;
+
+    fn on_call<F, X>(
+        &self,
+        call: Call,
+        meta: Arc<HistoricalRpc>,
+        next: F,
+    ) -> Either<Self::CallFuture, X>
+    where
+        F: Fn(Call, Arc<HistoricalRpc>) -> X + Send + Sync,
+        X: Future<Output = Option<Output>> + Send + 'static,
+    {
+        let (Some(sample_interval), Call::MethodCall(MethodCall { method, params, .. })) =
+            (self.sample_interval, &call)
+        else {
+            return Either::Right(next(call, meta));
+        };
+
+        let sampled = self.calls.fetch_add(1, Ordering::Relaxed) % sample_interval == 0;
+        let method = method.clone();
+        let mut params = serde_json::to_string(params).unwrap_or_default();
+        if let Some((end, _)) = params.char_indices().nth(LOG_PARAMS_MAX_LEN) {
+            params.truncate(end);
+        }
+        let start = Instant::now();
+        let output = next(call, meta);
+
+        Either::Left(Box::pin(async move {
+            let output = output.await;
+            let latency = start.elapsed();
+            match &output {
+                Some(Output::Failure(Failure { error, .. })) => {
+                    warn!(method, params, ?latency, %error, "RPC call failed");
+                }
+                Some(Output::Success(Success { result, .. })) if sampled => {
+                    let size = serde_json::to_vec(result).map_or(0, |result| result.len());
+                    info!(method, params, size, ?latency, "RPC call");
+                }
+                _ => {}
+            }
+
+            output
+        }))
+    }
+}
+
+/// Validates the account config fields shared by the account RPC methods,
+/// returning the requested encoding (or the default encoding if unspecified) &
+/// data slice.
+fn verify_account_config(
+    rpc: &HistoricalRpc,
+    config: Option<RpcAccountInfoConfig>,
+) -> Result<(UiAccountEncoding, Option<UiDataSliceConfig>)> {
+    let RpcAccountInfoConfig { encoding, data_slice, min_context_slot, .. } =
+        config.unwrap_or_default();
+    let slot = rpc.slot();
+    let encoding = encoding.unwrap_or(rpc.default_encoding);
+    let min_context_slot = min_context_slot.unwrap_or(0);
+    if encoding == UiAccountEncoding::Binary {
+        return Err(JsonRpcError::invalid_params(
+            "Binary encoding is deprecated; use base58 or base64 instead",
+        ));
+    }
+    if let Some(UiDataSliceConfig { length, .. }) = data_slice {
+        if length > rpc.data_slice_max {
+            return Err(JsonRpcError::invalid_params(format!(
+                "Data slice too long; requested={length}; max={}; see --data-slice-max",
+                rpc.data_slice_max
+            )));
+        }
+    }
+    if min_context_slot > slot {
+        return Err(JsonRpcError::invalid_params(format!(
+            "Min context slot not reached; requested={min_context_slot}; highest={slot}",
+        )));
+    }
+
+    Ok((encoding, data_slice))
+}
+
+/// Validates `getProgramAccounts` filters (`dataSize` & `memcmp` are
+/// supported).
+fn verify_program_filters(filters: &[RpcFilterType]) -> Result<()> {
+    for filter in filters {
+        if let RpcFilterType::TokenAccountState = filter {
+            return Err(JsonRpcError::invalid_params("tokenAccountState filter unsupported"));
+        }
+        filter
+            .verify()
+            .map_err(|err| JsonRpcError::invalid_params(format!("Invalid filter; err={err}")))?;
+    }
+
+    Ok(())
+}
+
+/// Encodes `account`, slicing its data by `data_slice` (clamped to the data,
+/// so out of range slices yield empty data).
+///
+/// `data_len` is the account's stored data length (reported as `space`), which
+/// exceeds `account.data.len()` if the account was read without its data.
+fn encode_account(
+    pubkey: &Pubkey,
+    account: &Account,
+    data_len: usize,
+    encoding: UiAccountEncoding,
+    data_slice: Option<UiDataSliceConfig>,
+) -> Result<UiAccount> {
+    let sliced_len =
+        data_slice.map_or(account.data.len(), |UiDataSliceConfig { offset, length }| {
+            account.data.len().saturating_sub(offset).min(length)
+        });
+    if encoding == UiAccountEncoding::Base58 && sliced_len > MAX_BASE58_BYTES {
+        return Err(JsonRpcError::invalid_params(format!(
+            "Encoded binary (base 58) data should be less than {MAX_BASE58_BYTES} bytes, please \
+             use Base64 encoding."
+        )));
+    }
+
+    let mut ui_account = encode_ui_account(pubkey, account, encoding, None, data_slice);
+    ui_account.space = Some(data_len as u64);
+
+    Ok(ui_account)
+}
+
+#[cfg(test)]
+mod tests {
+    use base64::prelude::{Engine, BASE64_STANDARD};
+    use serde_json::{json, Value};
+    use solana_rpc_client::rpc_client::RpcClient as BlockingRpcClient;
+    use solana_rpc_client_api::filter::Memcmp;
+    use solana_rpc_client_api::request::RpcRequest;
+
+    use super::*;
+    use crate::test_utils::{
+        append_vec_bytes, load_snapshot, open_snapshot, overlapping_snapshot, rpc_args,
+        source_args, SnapshotBuilder, TestAccount,
+    };
+
+    #[test]
+    fn get_account_info_over_http() {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(10, 0, vec![TestAccount::new(key, 42, owner, vec![1, 2, 3])])
+            .write();
+        let server =
+            load_snapshot(snapshot.path(), &[]).bind(rpc_args(&["--listen-addr", "127.0.0.1:0"]));
+        let client = BlockingRpcClient::new(format!("http://{}", server.address()));
+
+        let present: Value = client
+            .send(RpcRequest::GetAccountInfo, json!([key.to_string(), {"encoding": "base64"}]))
+            .unwrap();
+        assert_eq!(present["context"]["slot"], 10);
+        assert_eq!(present["value"]["lamports"], 42);
+        assert_eq!(present["value"]["owner"], owner.to_string());
+        assert_eq!(present["value"]["data"], json!([BASE64_STANDARD.encode([1, 2, 3]), "base64"]));
+
+        let absent: Value = client
+            .send(
+                RpcRequest::GetAccountInfo,
+                json!([Pubkey::new_unique().to_string(), {"encoding": "base64"}]),
+            )
+            .unwrap();
+        assert_eq!(absent["value"], Value::Null);
+
+        server.close();
+    }
+
+    #[test]
+    fn same_slot_prefers_higher_write_version() {
+        let key = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let version = |lamports, write_version| TestAccount {
+            write_version,
+            ..TestAccount::new(key, lamports, owner, Vec::new())
+        };
+
+        // NB: The newer write version is stored in the lower & then the higher id.
+        for (id_0, id_1) in [(version(2, 20), version(1, 10)), (version(1, 10), version(2, 20))] {
+            let snapshot = SnapshotBuilder::new(10)
+                .append_vec(10, 0, vec![id_0])
+                .append_vec(10, 1, vec![id_1])
+                .write();
+
+            for args in [&[][..], &["--packed-index"]] {
+                let rpc = load_snapshot(snapshot.path(), args);
+
+                assert_eq!(rpc.get_account(&key).unwrap().lamports, 2);
+            }
+        }
+    }
+
+    #[test]
+    fn index_holds_every_distinct_pubkey() {
+        let owner = Pubkey::new_unique();
+        let keys: Vec<_> = (0..25).map(|_| Pubkey::new_unique()).collect();
+        let accounts = |keys: &[Pubkey]| -> Vec<_> {
+            keys.iter()
+                .map(|key| TestAccount::new(*key, 1, owner, vec![0; 3]))
+                .collect()
+        };
+        // NB: Keys 10..15 are stored in several append vecs.
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(8, 0, accounts(&keys[..15]))
+            .append_vec(9, 0, accounts(&keys[10..20]))
+            .append_vec(9, 1, accounts(&keys[10..]))
+            .write();
+
+        let rpc = load_snapshot(snapshot.path(), &[]);
+
+        assert_eq!(rpc.len(), keys.len());
+        for key in &keys {
+            assert!(rpc.get_account(key).is_some());
+        }
+    }
+
+    #[test]
+    fn offset_lookup_matches_scan_lookup() {
+        let owner = Pubkey::new_unique();
+        let accounts: Vec<_> = (0..20u8)
+            .map(|i| TestAccount::new(Pubkey::new_unique(), u64::from(i), owner, vec![i; i.into()]))
+            .collect();
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(10, 0, accounts.clone())
+            .write();
+
+        // NB: The wide index reads at the stored offset, the packed index scans.
+        let wide = load_snapshot(snapshot.path(), &[]);
+        let packed = load_snapshot(snapshot.path(), &["--packed-index"]);
+        for TestAccount { pubkey, account, .. } in &accounts {
+            assert_eq!(wide.get_account(pubkey).as_ref(), Some(account));
+            assert_eq!(packed.get_account(pubkey).as_ref(), Some(account));
+        }
+    }
+
+    #[test]
+    fn zero_length_slice_reports_stored_space() {
+        let key = Pubkey::new_unique();
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(10, 0, vec![TestAccount::new(key, 1, Pubkey::new_unique(), vec![7; 100])])
+            .write();
+        let rpc = load_snapshot(snapshot.path(), &[]);
+
+        let (account, data_len) = rpc.try_get_account_meta(&key).unwrap().unwrap();
+        assert!(account.data.is_empty());
+        assert_eq!(data_len, 100);
+
+        let data_slice = Some(UiDataSliceConfig { offset: 10, length: 0 });
+        let ui_account =
+            encode_account(&key, &account, data_len, UiAccountEncoding::Base64, data_slice)
+                .unwrap();
+        assert_eq!(ui_account.data.decode().unwrap(), Vec::<u8>::new());
+        assert_eq!(ui_account.space, Some(100));
+        assert_eq!(ui_account.lamports, 1);
+    }
+
+    #[test]
+    fn parallel_index_matches_serial_index() {
+        let keys: Vec<_> = (0..50).map(|_| Pubkey::new_unique()).collect();
+        let snapshot = overlapping_snapshot(&keys);
+
+        let serial = load_snapshot(snapshot.path(), &["--num-threads", "1"]);
+        for threads in ["2", "8"] {
+            let parallel = load_snapshot(snapshot.path(), &["--num-threads", threads]);
+
+            assert_eq!(parallel.len(), serial.len());
+            for key in &keys {
+                assert_eq!(parallel.location(key), serial.location(key));
+                assert_eq!(parallel.get_account(key), serial.get_account(key));
+            }
+        }
+    }
+
+    /// A snapshot storing a correctly hashed, a corrupted (data changed after
+    /// hashing) & an unhashed account, in that order.
+    fn corrupted_snapshot() -> (tempfile::TempDir, [Pubkey; 3]) {
+        let keys = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let owner = Pubkey::new_unique();
+        let mut corrupted = TestAccount::new(keys[1], 2, owner, vec![4, 5, 6]).hashed();
+        corrupted.account.data[0] ^= 1;
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(
+                10,
+                0,
+                vec![
+                    TestAccount::new(keys[0], 1, owner, vec![1, 2, 3]).hashed(),
+                    corrupted,
+                    TestAccount::new(keys[2], 3, owner, vec![7, 8, 9]),
+                ],
+            )
+            .write();
+
+        (snapshot, keys)
+    }
+
+    #[test]
+    fn verify_hash_detects_corrupted_account() {
+        let (snapshot, keys) = corrupted_snapshot();
+        let extractor = open_snapshot(snapshot.path(), &[]);
+
+        let verified: Vec<_> = accounts_with_location(&extractor)
+            .map(|(_, _, account)| {
+                let account = account.access().unwrap();
+
+                (account.meta.pubkey, account.verify_hash())
+            })
+            .collect();
+        assert_eq!(verified, [(keys[0], true), (keys[1], false), (keys[2], true)]);
+
+        // NB: Hashes are only checked when requested.
+        let rpc = load_snapshot(snapshot.path(), &[]);
+        assert_eq!(rpc.len(), 3);
+        assert_eq!(rpc.get_account(&keys[1]).unwrap().data, [5, 5, 6]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Account hash mismatches; mismatches=1")]
+    fn verify_hashes_rejects_corrupted_account() {
+        let (snapshot, _) = corrupted_snapshot();
+
+        load_snapshot(snapshot.path(), &["--verify-hashes"]);
+    }
+
+    #[test]
+    fn index_filters_exclude_accounts() {
+        let owner = Pubkey::new_unique();
+        let other_owner = Pubkey::new_unique();
+        let [rich, poor, other, closed, reassigned, funded] =
+            [(); 6].map(|()| Pubkey::new_unique());
+        let snapshot = SnapshotBuilder::new(11)
+            .append_vec(
+                10,
+                0,
+                vec![
+                    TestAccount::new(rich, 100, owner, vec![]),
+                    TestAccount::new(poor, 1, owner, vec![]),
+                    TestAccount::new(other, 100, other_owner, vec![]),
+                    TestAccount::new(closed, 100, owner, vec![]),
+                    TestAccount::new(reassigned, 100, owner, vec![]),
+                    TestAccount::new(funded, 1, owner, vec![]),
+                ],
+            )
+            .append_vec(
+                11,
+                0,
+                vec![
+                    TestAccount::new(rich, 200, owner, vec![]),
+                    TestAccount::new(closed, 0, owner, vec![]),
+                    TestAccount::new(reassigned, 100, other_owner, vec![]),
+                    TestAccount::new(funded, 100, owner, vec![]),
+                ],
+            )
+            .write();
+        let owner_arg = owner.to_string();
+        let filter_args = ["--min-lamports", "50", "--owner", &owner_arg, "--program-owner-index"];
+
+        // NB: The older versions of `closed` & `reassigned` pass the filters, but the
+        // newest do not.
+        let rpc = load_snapshot(snapshot.path(), &filter_args);
+        assert_eq!(rpc.len(), 2);
+        assert_eq!(rpc.get_account(&rich).unwrap().lamports, 200);
+        assert_eq!(rpc.get_account(&funded).unwrap().lamports, 100);
+        for key in [poor, other, closed, reassigned] {
+            assert_eq!(rpc.location(&key), None);
+        }
+        let mut program_accounts: Vec<_> = rpc
+            .get_program_accounts(&owner, &[])
+            .unwrap()
+            .into_iter()
+            .map(|(key, _)| key)
+            .collect();
+        program_accounts.sort_unstable();
+        let mut expected = [rich, funded];
+        expected.sort_unstable();
+        assert_eq!(program_accounts, expected);
+
+        // Every stored version is scanned, but only the kept keys are counted as
+        // unique.
+        let extractor = open_snapshot(snapshot.path(), &[]);
+        let args = source_args(snapshot.path(), &filter_args).index;
+        let (accounts_bar, unique_accounts_bar) = (ProgressBar::hidden(), ProgressBar::hidden());
+        let (account_index, ..) = HistoricalRpc::build_index(
+            &extractor,
+            &args,
+            &accounts_bar,
+            &unique_accounts_bar,
+            &AtomicBool::default(),
+        )
+        .unwrap();
+        assert_eq!(account_index.len(), 2);
+        assert_eq!(accounts_bar.position(), 10);
+        assert_eq!(unique_accounts_bar.position(), 2);
+    }
+
+    #[test]
+    fn estimated_accounts_within_order_of_magnitude() {
+        let owner = Pubkey::new_unique();
+        let mut builder = SnapshotBuilder::new(10);
+        for id in 0..4 {
+            // NB: Mostly small accounts plus token account sized data.
+            let accounts = (0..250)
+                .map(|i| {
+                    let data_len = if i % 5 == 0 { 165 } else { i % 64 };
+
+                    TestAccount::new(Pubkey::new_unique(), 1, owner, vec![0; data_len])
+                })
+                .collect();
+            builder = builder.append_vec(10, id, accounts);
+        }
+        let snapshot = builder.write();
+
+        let estimate = estimate_accounts(&open_snapshot(snapshot.path(), &[]));
+        assert!((100..=10_000).contains(&estimate), "estimate={estimate}");
+    }
+
+    #[test]
+    fn data_len_filters_apply_to_resolved_version() {
+        let owner = Pubkey::new_unique();
+        let [grown, shrunk] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let snapshot = SnapshotBuilder::new(11)
+            .append_vec(
+                10,
+                0,
+                vec![
+                    TestAccount::new(grown, 1, owner, vec![0; 10]),
+                    TestAccount::new(shrunk, 1, owner, vec![0; 100]),
+                ],
+            )
+            .append_vec(
+                11,
+                0,
+                vec![
+                    TestAccount::new(grown, 1, owner, vec![0; 100]),
+                    TestAccount::new(shrunk, 1, owner, vec![0; 10]),
+                ],
+            )
+            .write();
+
+        // NB: Each key's pre-realloc version passes one filter, but it is never
+        // served in place of the newest version.
+        let rpc = load_snapshot(snapshot.path(), &["--max-data-len", "50"]);
+        assert_eq!(rpc.location(&grown), None);
+        assert_eq!(rpc.get_account(&shrunk).unwrap().data.len(), 10);
+
+        let rpc = load_snapshot(snapshot.path(), &["--min-data-len", "50"]);
+        assert_eq!(rpc.get_account(&grown).unwrap().data.len(), 100);
+        assert_eq!(rpc.location(&shrunk), None);
+    }
+
+    #[test]
+    fn get_accounts_reads_indexed_version() {
+        let owner = Pubkey::new_unique();
+        let [duplicated, single] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        // NB: The slot has two append vecs, so the higher write version of
+        // `duplicated` (stored second) wins.
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(
+                10,
+                0,
+                vec![
+                    TestAccount {
+                        write_version: 1,
+                        ..TestAccount::new(duplicated, 1, owner, vec![1])
+                    },
+                    TestAccount::new(single, 3, owner, vec![3]),
+                    TestAccount {
+                        write_version: 5,
+                        ..TestAccount::new(duplicated, 2, owner, vec![2])
+                    },
+                ],
+            )
+            .append_vec(10, 1, vec![TestAccount::new(Pubkey::new_unique(), 4, owner, vec![])])
+            .write();
+
+        let rpc = load_snapshot(snapshot.path(), &[]);
+        assert_eq!(rpc.get_account(&duplicated).unwrap().lamports, 2);
+        let keys = [duplicated, Pubkey::new_unique(), single, duplicated];
+        let expected: Vec<_> = keys.iter().map(|key| rpc.get_account(key)).collect();
+        assert_eq!(rpc.get_accounts(&keys), expected);
+
+        // NB: The packed index stores no offsets, so both read the first version.
+        let rpc = load_snapshot(snapshot.path(), &["--packed-index"]);
+        let expected: Vec<_> = keys.iter().map(|key| rpc.get_account(key)).collect();
+        assert_eq!(rpc.get_accounts(&keys), expected);
+    }
+
+    #[test]
+    fn index_ignores_append_vec_slack() {
+        let owner = Pubkey::new_unique();
+        let [stored, stale] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        // NB: The slack holds a parseable account followed by junk, neither of which is
+        // within the manifest's `accounts_current_len`.
+        let mut slack = append_vec_bytes(&[TestAccount::new(stale, 2, owner, vec![2])]);
+        slack.extend([0xff; 13]);
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec_with_slack(10, 0, vec![TestAccount::new(stored, 1, owner, vec![1])], slack)
+            .write();
+
+        // NB: `--strict-scan` aborts unless exactly `accounts_current_len` bytes are
+        // parsed.
+        for args in [&["--strict-scan"][..], &["--strict-scan", "--packed-index"]] {
+            let rpc = load_snapshot(snapshot.path(), args);
+            assert_eq!(rpc.len(), 1);
+            assert_eq!(rpc.get_account(&stored).unwrap().lamports, 1);
+            assert_eq!(rpc.location(&stale), None);
+        }
+    }
+
+    #[test]
+    fn saved_index_is_independent_of_thread_count() {
+        let keys: Vec<_> = (0..50).map(|_| Pubkey::new_unique()).collect();
+        let snapshot = overlapping_snapshot(&keys);
+        let dir = tempfile::tempdir().unwrap();
+
+        for packed in [false, true] {
+            let files: Vec<_> = ["1", "2", "8"]
+                .into_iter()
+                .map(|threads| {
+                    let path = dir.path().join(format!("{packed}-{threads}.idx"));
+                    let path_arg = path.to_str().unwrap();
+                    let mut args = vec!["--num-threads", threads, "--save-index", path_arg];
+                    if packed {
+                        args.push("--packed-index");
+                    }
+                    load_snapshot(snapshot.path(), &args);
+
+                    std::fs::read(&path).unwrap()
+                })
+                .collect();
+
+            assert_eq!(files[0], files[1]);
+            assert_eq!(files[0], files[2]);
+        }
+    }
+
+    #[test]
+    fn encode_account_clamps_data_slice() {
+        let key = Pubkey::new_unique();
+        let account = Account { lamports: 1, data: (0..10).collect(), ..Account::default() };
+        let encode = |offset, length| {
+            let data_slice = Some(UiDataSliceConfig { offset, length });
+
+            encode_account(&key, &account, 10, UiAccountEncoding::Base64, data_slice)
+                .unwrap()
+                .data
+                .decode()
+                .unwrap()
+        };
+
+        assert_eq!(encode(2, 3), [2, 3, 4]);
+        assert_eq!(encode(8, 5), [8, 9]);
+        assert_eq!(encode(10, 5), Vec::<u8>::new());
+        assert_eq!(encode(20, 5), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn get_multiple_accounts_preserves_order() {
+        let keys: Vec<_> = (0..3).map(|_| Pubkey::new_unique()).collect();
+        let absent = Pubkey::new_unique();
+        let owner = Pubkey::new_unique();
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(
+                10,
+                0,
+                keys.iter()
+                    .zip(1..)
+                    .map(|(key, i)| TestAccount::new(*key, i, owner, vec![i as u8; i as usize]))
+                    .collect(),
+            )
+            .write();
+        // NB: The packed index exercises the scan path.
+        for args in [&[][..], &["--packed-index"]] {
+            let rpc = Arc::new(load_snapshot(snapshot.path(), args));
+            let request = [keys[2], absent, keys[0], keys[1]];
+            let request_strs: Vec<_> = request.iter().map(ToString::to_string).collect();
+
+            for data_slice in [None, Some(UiDataSliceConfig { offset: 0, length: 0 })] {
+                let config = RpcAccountInfoConfig {
+                    encoding: Some(UiAccountEncoding::Base64),
+                    data_slice,
+                    ..RpcAccountInfoConfig::default()
+                };
+                let accounts = AccountsRpcImpl
+                    .get_multiple_accounts(rpc.clone(), request_strs.clone(), Some(config))
+                    .unwrap()
+                    .value;
+
+                let lamports: Vec<_> = accounts
+                    .iter()
+                    .map(|account| account.as_ref().map(|account| account.lamports))
+                    .collect();
+                assert_eq!(lamports, [Some(3), None, Some(1), Some(2)]);
+                for (account, space) in accounts.iter().zip([3, 0, 1, 2]) {
+                    let Some(account) = account else {
+                        continue;
+                    };
+                    assert_eq!(account.space, Some(space));
+                    assert_eq!(
+                        account.data.decode().unwrap().len(),
+                        if data_slice.is_some() { 0 } else { space as usize }
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn get_program_accounts_filters_by_owner_and_data_size() {
+        let (program_a, program_b) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let keys: Vec<_> = (0..5).map(|_| Pubkey::new_unique()).collect();
+        // NB: keys[4] moves from program A to program B.
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(
+                9,
+                0,
+                vec![
+                    TestAccount::new(keys[0], 1, program_a, vec![1, 2, 3]),
+                    TestAccount::new(keys[1], 1, program_a, vec![4, 5, 6]),
+                    TestAccount::new(keys[2], 1, program_a, vec![1, 2, 3, 4, 5]),
+                    TestAccount::new(keys[3], 1, program_b, vec![1, 2, 3]),
+                    TestAccount::new(keys[4], 1, program_a, vec![1, 2, 3]),
+                ],
+            )
+            .append_vec(10, 0, vec![TestAccount::new(keys[4], 1, program_b, vec![1, 2, 3])])
+            .write();
+        let rpc = load_snapshot(snapshot.path(), &["--program-owner-index"]);
+        let program_accounts = |program: &Pubkey, filters: &[RpcFilterType]| {
+            let mut keys: Vec<_> = rpc
+                .get_program_accounts(program, filters)
+                .unwrap()
+                .into_iter()
+                .map(|(key, _)| key)
+                .collect();
+            keys.sort_unstable();
+
+            keys
+        };
+        let sorted = |mut keys: Vec<Pubkey>| {
+            keys.sort_unstable();
+
+            keys
+        };
+
+        assert_eq!(program_accounts(&program_a, &[]), sorted(keys[..3].to_vec()));
+        assert_eq!(program_accounts(&program_b, &[]), sorted(keys[3..].to_vec()));
+        assert_eq!(
+            program_accounts(&program_a, &[RpcFilterType::DataSize(3)]),
+            sorted(keys[..2].to_vec())
+        );
+        assert_eq!(
+            program_accounts(
+                &program_a,
+                &[
+                    RpcFilterType::DataSize(3),
+                    RpcFilterType::Memcmp(Memcmp::new_raw_bytes(0, vec![4]))
+                ]
+            ),
+            [keys[1]]
+        );
+        assert!(program_accounts(&Pubkey::new_unique(), &[]).is_empty());
+    }
+
+    #[test]
+    fn bind_ephemeral_port() {
+        let snapshot = SnapshotBuilder::new(10).write();
+        let server =
+            load_snapshot(snapshot.path(), &[]).bind(rpc_args(&["--listen-addr", "127.0.0.1:0"]));
+
+        assert_ne!(server.address().port(), 0);
+        std::net::TcpStream::connect(server.address()).unwrap();
+
+        server.close();
+    }
+
+    #[test]
+    #[should_panic(expected = "Failed to bind RPC server; listen_addr=127.0.0.1:")]
+    fn bind_reports_listen_addr_on_failure() {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let listen_addr = listener.local_addr().unwrap().to_string();
+        let snapshot = SnapshotBuilder::new(10).write();
+
+        load_snapshot(snapshot.path(), &[]).bind(rpc_args(&["--listen-addr", &listen_addr]));
+    }
+
+    #[test]
+    fn slot_precedes_write_version() {
+        let (a, b, c) = (Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique());
+        let owner = Pubkey::new_unique();
+        let version = |key, lamports, write_version| TestAccount {
+            write_version,
+            ..TestAccount::new(key, lamports, owner, Vec::new())
+        };
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(9, 0, vec![version(a, 1, 100)])
+            .append_vec(10, 0, vec![version(a, 2, 1), version(b, 3, 5)])
+            .append_vec(10, 1, vec![version(b, 4, 5), version(c, 6, 7)])
+            .append_vec(10, 2, vec![version(c, 5, 3)])
+            .write();
+        let lamports =
+            |rpc: &HistoricalRpc| [a, b, c].map(|key| rpc.get_account(&key).unwrap().lamports);
+
+        for threads in ["1", "4"] {
+            let newest = load_snapshot(snapshot.path(), &["--num-threads", threads]);
+            assert_eq!(lamports(&newest), [2, 3, 6]);
+
+            let oldest = load_snapshot(
+                snapshot.path(),
+                &["--num-threads", threads, "--index-policy", "oldest"],
+            );
+            assert_eq!(lamports(&oldest), [1, 3, 5]);
+        }
+    }
+}
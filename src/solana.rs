@@ -18,14 +18,14 @@ use std::collections::{HashMap, HashSet};
 use std::io::Read;
 
 use bincode::Options;
-use serde::de::DeserializeOwned;
-use serde::{Deserialize, Serialize};
+use serde::de::{DeserializeOwned, Error as _, SeqAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize};
 use solana_accounts_db::account_storage::meta::StoredMetaWriteVersion;
 use solana_accounts_db::accounts_db::stats::BankHashStats;
 use solana_accounts_db::ancestors::AncestorsForSerialization;
 use solana_accounts_db::blockhash_queue::BlockhashQueue;
 use solana_frozen_abi_macro::AbiExample;
-use solana_runtime::epoch_stakes::EpochStakes;
+use solana_runtime::epoch_stakes::{EpochStakes, VersionedEpochStakes};
 use solana_runtime::stakes::Stakes;
 use solana_sdk::clock::{Epoch, UnixTimestamp};
 use solana_sdk::deserialize_utils::default_on_eof;
@@ -54,6 +54,7 @@ where
 }
 
 #[derive(Default, PartialEq, Eq, Debug, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
 struct UnusedAccounts {
     unused1: HashSet<Pubkey>,
     unused2: HashSet<Pubkey>,
@@ -61,6 +62,7 @@ struct UnusedAccounts {
 }
 
 #[derive(Deserialize)]
+#[cfg_attr(test, derive(Default, Serialize))]
 #[allow(dead_code)]
 pub(crate) struct DeserializableVersionedBank {
     pub(crate) blockhash_queue: BlockhashQueue,
@@ -106,6 +108,7 @@ pub(crate) struct BankHashInfo {
 }
 
 #[derive(Clone, Debug, Default, Deserialize, PartialEq)]
+#[cfg_attr(test, derive(Serialize))]
 pub(crate) struct AccountsDbFields<T>(
     pub(crate) HashMap<Slot, Vec<T>>,
     pub(crate) StoredMetaWriteVersion,
@@ -123,7 +126,65 @@ pub(crate) struct AccountsDbFields<T>(
 pub(crate) type SerializedAppendVecId = usize;
 
 #[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize)]
+#[cfg_attr(test, derive(Serialize))]
 pub(crate) struct SerializableAccountStorageEntry {
     pub(crate) id: SerializedAppendVecId,
     pub(crate) accounts_current_len: usize,
 }
+
+/// Optional manifest fields following [`AccountsDbFields`] (after the leading
+/// `lamports_per_signature: u64`).
+#[derive(Deserialize)]
+#[allow(dead_code)]
+pub(crate) struct ExtraFieldsToDeserialize {
+    #[serde(deserialize_with = "default_on_eof")]
+    pub(crate) incremental_snapshot_persistence: Option<BankIncrementalSnapshotPersistence>,
+    #[serde(deserialize_with = "default_on_eof")]
+    pub(crate) epoch_accounts_hash: Option<Hash>,
+    #[serde(deserialize_with = "default_on_eof")]
+    versioned_epoch_stakes: HashMap<u64, VersionedEpochStakes>,
+    #[serde(deserialize_with = "default_on_eof")]
+    pub(crate) accounts_lt_hash: Option<SerializableAccountsLtHash>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Eq)]
+#[cfg_attr(test, derive(Serialize))]
+#[allow(dead_code)]
+pub(crate) struct BankIncrementalSnapshotPersistence {
+    pub(crate) full_slot: Slot,
+    pub(crate) full_hash: Hash,
+    pub(crate) full_capitalization: u64,
+    pub(crate) incremental_hash: Hash,
+    pub(crate) incremental_capitalization: u64,
+}
+
+/// Number of `u16` elements in an accounts lattice hash.
+const LT_HASH_NUM_ELEMENTS: usize = 1024;
+
+/// The accounts lattice hash; only its presence is retained.
+pub(crate) struct SerializableAccountsLtHash;
+
+impl<'de> Deserialize<'de> for SerializableAccountsLtHash {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct LtHashVisitor;
+
+        impl<'de> Visitor<'de> for LtHashVisitor {
+            type Value = SerializableAccountsLtHash;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "an array of {LT_HASH_NUM_ELEMENTS} u16s")
+            }
+
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                for i in 0..LT_HASH_NUM_ELEMENTS {
+                    seq.next_element::<u16>()?
+                        .ok_or_else(|| A::Error::invalid_length(i, &self))?;
+                }
+
+                Ok(SerializableAccountsLtHash)
+            }
+        }
+
+        deserializer.deserialize_tuple(LT_HASH_NUM_ELEMENTS, LtHashVisitor)
+    }
+}
@@ -0,0 +1,306 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::hash::{BuildHasher, BuildHasherDefault, DefaultHasher};
+
+use hashbrown::{HashMap, HashSet};
+use serde::Serialize;
+use solana_sdk::clock::Slot;
+use solana_sdk::pubkey::Pubkey;
+
+use crate::append_vec::StoredAccountMeta;
+use crate::filter::AccountFilter;
+use crate::unpacked::UnpackedSnapshotExtractor;
+use crate::utils::accounts_with_location;
+
+/// Aggregates over every stored account version (not only the newest),
+/// gathered in a single pass without building an index.
+#[derive(Debug, Serialize)]
+pub(crate) struct AccountStats {
+    accounts: u64,
+    /// Approximate (within ~1%) unless counted with `--exact-unique`.
+    unique_pubkeys: usize,
+    lamports: u128,
+    data_bytes: u64,
+    /// The owners with the most accounts, descending.
+    top_owners: Vec<OwnerStats>,
+    /// The accounts with the most data, descending.
+    largest_accounts: Vec<LargestAccount>,
+}
+
+#[derive(Debug, Serialize)]
+struct OwnerStats {
+    owner: String,
+    accounts: u64,
+    data_bytes: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct LargestAccount {
+    pubkey: String,
+    owner: String,
+    slot: Slot,
+    data_len: u64,
+}
+
+impl AccountStats {
+    /// Scans every account matching `filter`, keeping the `top` owners &
+    /// largest accounts.
+    pub(crate) fn new(
+        extractor: &UnpackedSnapshotExtractor,
+        filter: &AccountFilter,
+        top: usize,
+        exact_unique: bool,
+    ) -> Self {
+        let mut stats = StatsAccumulator::new(top, exact_unique);
+        for (slot, _, account) in accounts_with_location(extractor) {
+            let account = account.access().unwrap();
+            if filter.matches(&account) {
+                stats.add(slot, &account);
+            }
+        }
+        extractor.log_scan_errors();
+
+        stats.finish()
+    }
+
+    pub(crate) fn print(&self, json: bool) {
+        if json {
+            println!("{}", serde_json::to_string_pretty(self).unwrap());
+        } else {
+            println!("{self:#?}");
+        }
+    }
+}
+
+/// Accumulates [`AccountStats`] one stored account version at a time.
+struct StatsAccumulator {
+    top: usize,
+    accounts: u64,
+    lamports: u128,
+    data_bytes: u64,
+    pubkeys: UniquePubkeys,
+    owners: HashMap<Pubkey, (u64, u64)>,
+    /// Min-heap of the largest accounts seen so far, bounded to `top` entries.
+    largest: BinaryHeap<Reverse<(u64, Pubkey, Pubkey, Slot)>>,
+}
+
+impl StatsAccumulator {
+    fn new(top: usize, exact_unique: bool) -> Self {
+        StatsAccumulator {
+            top,
+            accounts: 0,
+            lamports: 0,
+            data_bytes: 0,
+            pubkeys: if exact_unique {
+                UniquePubkeys::Exact(HashSet::new())
+            } else {
+                UniquePubkeys::Approximate(HyperLogLog::new())
+            },
+            owners: HashMap::new(),
+            largest: BinaryHeap::with_capacity(top + 1),
+        }
+    }
+
+    fn add(&mut self, slot: Slot, account: &StoredAccountMeta) {
+        let data_len = account.meta.data_len;
+        self.accounts += 1;
+        self.lamports += u128::from(account.account_meta.lamports);
+        self.data_bytes += data_len;
+        self.pubkeys.insert(account.meta.pubkey);
+
+        let owner = self.owners.entry(account.account_meta.owner).or_default();
+        owner.0 += 1;
+        owner.1 += data_len;
+
+        self.largest.push(Reverse((
+            data_len,
+            account.meta.pubkey,
+            account.account_meta.owner,
+            slot,
+        )));
+        if self.largest.len() > self.top {
+            self.largest.pop();
+        }
+    }
+
+    fn finish(self) -> AccountStats {
+        let mut owners: Vec<_> = self.owners.into_iter().collect();
+        owners.sort_unstable_by(|(a_owner, (a_accounts, _)), (b_owner, (b_accounts, _))| {
+            b_accounts
+                .cmp(a_accounts)
+                .then_with(|| a_owner.cmp(b_owner))
+        });
+        owners.truncate(self.top);
+
+        AccountStats {
+            accounts: self.accounts,
+            unique_pubkeys: self.pubkeys.len(),
+            lamports: self.lamports,
+            data_bytes: self.data_bytes,
+            top_owners: owners
+                .into_iter()
+                .map(|(owner, (accounts, data_bytes))| OwnerStats {
+                    owner: owner.to_string(),
+                    accounts,
+                    data_bytes,
+                })
+                .collect(),
+            // NB: Sorting the reversed entries ascending yields the largest first.
+            largest_accounts: self
+                .largest
+                .into_sorted_vec()
+                .into_iter()
+                .map(|Reverse((data_len, pubkey, owner, slot))| LargestAccount {
+                    pubkey: pubkey.to_string(),
+                    owner: owner.to_string(),
+                    slot,
+                    data_len,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Counts distinct pubkeys, either exactly (memory grows with the number of
+/// pubkeys) or approximately in constant memory.
+enum UniquePubkeys {
+    Exact(HashSet<Pubkey>),
+    Approximate(HyperLogLog),
+}
+
+impl UniquePubkeys {
+    fn insert(&mut self, pubkey: Pubkey) {
+        match self {
+            UniquePubkeys::Exact(pubkeys) => {
+                pubkeys.insert(pubkey);
+            }
+            UniquePubkeys::Approximate(pubkeys) => pubkeys.insert(&pubkey),
+        }
+    }
+
+    fn len(&self) -> usize {
+        match self {
+            UniquePubkeys::Exact(pubkeys) => pubkeys.len(),
+            UniquePubkeys::Approximate(pubkeys) => pubkeys.estimate(),
+        }
+    }
+}
+
+/// Bits of the hash selecting a register (2^14 registers, ~16KiB, for a
+/// standard error of ~0.8%).
+const HLL_PRECISION: u32 = 14;
+const HLL_REGISTERS: usize = 1 << HLL_PRECISION;
+
+/// A HyperLogLog cardinality estimator.
+struct HyperLogLog {
+    /// The maximum rank (leading zeros + 1) seen by each register.
+    registers: Box<[u8]>,
+}
+
+impl HyperLogLog {
+    fn new() -> Self {
+        HyperLogLog { registers: vec![0; HLL_REGISTERS].into_boxed_slice() }
+    }
+
+    fn insert(&mut self, pubkey: &Pubkey) {
+        // NB: Pubkeys are not necessarily uniform (e.g. vanity addresses), so hash
+        // them with a fixed key to keep the estimate deterministic.
+        let hash = BuildHasherDefault::<DefaultHasher>::default().hash_one(pubkey);
+        let register = (hash >> (64 - HLL_PRECISION)) as usize;
+        let rank = ((hash << HLL_PRECISION) | (1 << (HLL_PRECISION - 1))).leading_zeros() + 1;
+
+        let register = &mut self.registers[register];
+        *register = (*register).max(rank as u8);
+    }
+
+    fn estimate(&self) -> usize {
+        let registers = HLL_REGISTERS as f64;
+        let alpha = 0.7213 / (1.0 + 1.079 / registers);
+        let sum: f64 = self
+            .registers
+            .iter()
+            .map(|rank| 2f64.powi(-i32::from(*rank)))
+            .sum();
+        let estimate = alpha * registers * registers / sum;
+
+        // NB: Small cardinalities are estimated more accurately by linear counting.
+        let zeros = self.registers.iter().filter(|rank| **rank == 0).count();
+        if estimate <= 2.5 * registers && zeros > 0 {
+            return (registers * (registers / zeros as f64).ln()).round() as usize;
+        }
+
+        estimate.round() as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_accounts_db::account_storage::meta::{AccountMeta, StoredMeta};
+    use solana_sdk::hash::Hash;
+
+    use super::*;
+
+    fn add(stats: &mut StatsAccumulator, slot: Slot, pubkey: Pubkey, owner: Pubkey, data_len: u64) {
+        let meta = StoredMeta { write_version_obsolete: 0, data_len, pubkey };
+        let account_meta = AccountMeta { lamports: 10, rent_epoch: 0, owner, executable: false };
+        let data = vec![0; data_len as usize];
+        stats.add(
+            slot,
+            &StoredAccountMeta {
+                meta: &meta,
+                account_meta: &account_meta,
+                data: &data,
+                offset: 0,
+                stored_size: 0,
+                hash: &Hash::default(),
+            },
+        );
+    }
+
+    #[test]
+    fn accumulates_account_stats() {
+        let [big_owner, small_owner] = [Pubkey::new_unique(), Pubkey::new_unique()];
+        let [a, b, c] = [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        for exact_unique in [false, true] {
+            let mut stats = StatsAccumulator::new(2, exact_unique);
+            add(&mut stats, 1, a, big_owner, 10);
+            add(&mut stats, 2, a, big_owner, 30);
+            add(&mut stats, 2, b, big_owner, 5);
+            add(&mut stats, 3, c, small_owner, 20);
+            let stats = stats.finish();
+
+            assert_eq!(stats.accounts, 4);
+            assert_eq!(stats.unique_pubkeys, 3);
+            assert_eq!(stats.lamports, 40);
+            assert_eq!(stats.data_bytes, 65);
+            let top_owners: Vec<_> = stats
+                .top_owners
+                .iter()
+                .map(|owner| (owner.owner.clone(), owner.accounts, owner.data_bytes))
+                .collect();
+            assert_eq!(
+                top_owners,
+                [(big_owner.to_string(), 3, 45), (small_owner.to_string(), 1, 20)]
+            );
+            let largest: Vec<_> = stats
+                .largest_accounts
+                .iter()
+                .map(|account| (account.pubkey.clone(), account.slot, account.data_len))
+                .collect();
+            assert_eq!(largest, [(a.to_string(), 2, 30), (c.to_string(), 3, 20)]);
+        }
+    }
+
+    #[test]
+    fn approximate_unique_pubkeys_within_error_bound() {
+        let pubkeys: Vec<_> = (0..100_000).map(|_| Pubkey::new_unique()).collect();
+        let mut hll = HyperLogLog::new();
+        // NB: Repeated versions of a pubkey do not change the estimate.
+        for pubkey in pubkeys.iter().chain(&pubkeys[..50_000]) {
+            hll.insert(pubkey);
+        }
+
+        let error = hll.estimate().abs_diff(pubkeys.len()) as f64 / pubkeys.len() as f64;
+        assert!(error < 0.03, "error={error}");
+    }
+}
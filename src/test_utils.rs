@@ -0,0 +1,315 @@
+//! In-process snapshot fixtures for unit tests.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+use clap::Parser;
+use indicatif::ProgressBar;
+use solana_runtime::snapshot_utils::{SNAPSHOT_STATUS_CACHE_FILENAME, SNAPSHOT_VERSION_FILENAME};
+use solana_sdk::account::Account;
+use solana_sdk::clock::Slot;
+use solana_sdk::hash::Hash;
+use solana_sdk::pubkey::Pubkey;
+use tempfile::TempDir;
+
+use crate::args::{Action, Args, RpcArgs};
+use crate::rpc::HistoricalRpc;
+use crate::solana::{
+    AccountsDbFields, BankHashInfo, BankIncrementalSnapshotPersistence,
+    DeserializableVersionedBank, SerializableAccountStorageEntry,
+};
+use crate::unpacked::UnpackedSnapshotExtractor;
+use crate::utils::LoadProgressTracking;
+
+/// A single stored account version.
+#[derive(Debug, Clone)]
+pub(crate) struct TestAccount {
+    pub(crate) pubkey: Pubkey,
+    pub(crate) write_version: u64,
+    pub(crate) account: Account,
+    pub(crate) hash: Hash,
+}
+
+impl TestAccount {
+    pub(crate) fn new(pubkey: Pubkey, lamports: u64, owner: Pubkey, data: Vec<u8>) -> Self {
+        TestAccount {
+            pubkey,
+            write_version: 0,
+            account: Account { lamports, data, owner, executable: false, rent_epoch: 0 },
+            hash: Hash::default(),
+        }
+    }
+
+    /// Stores the account with the hash the runtime computes for it.
+    pub(crate) fn hashed(mut self) -> Self {
+        let mut hasher = blake3::Hasher::new();
+        hasher.update(&self.account.lamports.to_le_bytes());
+        hasher.update(&self.account.rent_epoch.to_le_bytes());
+        hasher.update(&self.account.data);
+        hasher.update(&[u8::from(self.account.executable)]);
+        hasher.update(self.account.owner.as_ref());
+        hasher.update(self.pubkey.as_ref());
+        self.hash = Hash::new_from_array(hasher.finalize().into());
+
+        self
+    }
+}
+
+/// A snapshot whose append vecs (across & within slots) repeatedly store the
+/// same keys at different write versions & lamports.
+pub(crate) fn overlapping_snapshot(keys: &[Pubkey]) -> TempDir {
+    let owner = Pubkey::new_unique();
+    let mut builder = SnapshotBuilder::new(20);
+    for slot in 10..20 {
+        for id in 0..3 {
+            let accounts = keys
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| (i + slot as usize + id) % 3 != 0)
+                .map(|(i, key)| TestAccount {
+                    write_version: ((i + id) % 4) as u64,
+                    ..TestAccount::new(*key, slot * 10 + id as u64, owner, vec![i as u8])
+                })
+                .collect();
+            builder = builder.append_vec(slot, id, accounts);
+        }
+    }
+
+    builder.write()
+}
+
+/// Serializes `accounts` in the append vec layout (`StoredMeta`, `AccountMeta`,
+/// hash & data, each record `u64` aligned).
+pub(crate) fn append_vec_bytes(accounts: &[TestAccount]) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    for TestAccount { pubkey, write_version, account, hash } in accounts {
+        bytes.extend(write_version.to_le_bytes());
+        bytes.extend((account.data.len() as u64).to_le_bytes());
+        bytes.extend(pubkey.as_ref());
+        bytes.extend(account.lamports.to_le_bytes());
+        bytes.extend(account.rent_epoch.to_le_bytes());
+        bytes.extend(account.owner.as_ref());
+        bytes.push(u8::from(account.executable));
+        bytes.resize(u64_align(bytes.len()), 0);
+        bytes.extend(hash.as_ref());
+        bytes.extend(&account.data);
+        bytes.resize(u64_align(bytes.len()), 0);
+    }
+
+    bytes
+}
+
+const fn u64_align(len: usize) -> usize {
+    (len + 7) & !7
+}
+
+struct TestAppendVec {
+    slot: Slot,
+    id: usize,
+    accounts: Vec<TestAccount>,
+    /// Bytes written after the accounts (beyond `accounts_current_len`).
+    slack: Vec<u8>,
+}
+
+/// Writes an unpacked snapshot (manifest, status cache, version file & append
+/// vecs) to a temporary directory.
+pub(crate) struct SnapshotBuilder {
+    slot: Slot,
+    version: Option<String>,
+    append_vecs: Vec<TestAppendVec>,
+    incremental_persistence: Option<BankIncrementalSnapshotPersistence>,
+    accounts_lt_hash: bool,
+    trailing_fields: bool,
+    trailing_bytes: Vec<u8>,
+}
+
+impl SnapshotBuilder {
+    pub(crate) fn new(slot: Slot) -> Self {
+        SnapshotBuilder {
+            slot,
+            version: Some("1.2.0".to_string()),
+            append_vecs: Vec::new(),
+            incremental_persistence: None,
+            accounts_lt_hash: false,
+            trailing_fields: true,
+            trailing_bytes: Vec::new(),
+        }
+    }
+
+    pub(crate) fn append_vec(self, slot: Slot, id: usize, accounts: Vec<TestAccount>) -> Self {
+        self.append_vec_with_slack(slot, id, accounts, Vec::new())
+    }
+
+    /// Like [`SnapshotBuilder::append_vec`], but writes `slack` to the file
+    /// after the accounts (beyond the manifest's `accounts_current_len`).
+    pub(crate) fn append_vec_with_slack(
+        mut self,
+        slot: Slot,
+        id: usize,
+        accounts: Vec<TestAccount>,
+        slack: Vec<u8>,
+    ) -> Self {
+        self.append_vecs
+            .push(TestAppendVec { slot, id, accounts, slack });
+
+        self
+    }
+
+    /// Sets the contents of the version file (`None` omits the file).
+    pub(crate) fn version(mut self, version: Option<&str>) -> Self {
+        self.version = version.map(str::to_string);
+
+        self
+    }
+
+    /// Marks the snapshot as an incremental snapshot of the full snapshot at
+    /// `full_slot`.
+    pub(crate) fn incremental(mut self, full_slot: Slot) -> Self {
+        self.incremental_persistence =
+            Some(BankIncrementalSnapshotPersistence { full_slot, ..Default::default() });
+
+        self
+    }
+
+    /// Stores an (all zero) accounts lattice hash in the manifest.
+    pub(crate) fn accounts_lt_hash(mut self) -> Self {
+        self.accounts_lt_hash = true;
+
+        self
+    }
+
+    /// Ends the manifest after the accounts DB fields (as older snapshots do).
+    pub(crate) fn without_trailing_fields(mut self) -> Self {
+        self.trailing_fields = false;
+
+        self
+    }
+
+    /// Appends `bytes` to the manifest after all known fields.
+    pub(crate) fn trailing_bytes(mut self, bytes: Vec<u8>) -> Self {
+        self.trailing_bytes = bytes;
+
+        self
+    }
+
+    pub(crate) fn write(self) -> TempDir {
+        let root = tempfile::tempdir().unwrap();
+
+        let snapshots = root.path().join("snapshots");
+        let bank_dir = snapshots.join(self.slot.to_string());
+        std::fs::create_dir_all(&bank_dir).unwrap();
+        File::create(snapshots.join(SNAPSHOT_STATUS_CACHE_FILENAME)).unwrap();
+        if let Some(version) = &self.version {
+            std::fs::write(root.path().join(SNAPSHOT_VERSION_FILENAME), version).unwrap();
+        }
+
+        let accounts = root.path().join("accounts");
+        std::fs::create_dir(&accounts).unwrap();
+        let mut storages: HashMap<Slot, Vec<SerializableAccountStorageEntry>> = HashMap::new();
+        for TestAppendVec { slot, id, accounts: stored, slack } in &self.append_vecs {
+            let mut bytes = append_vec_bytes(stored);
+            storages
+                .entry(*slot)
+                .or_default()
+                .push(SerializableAccountStorageEntry {
+                    id: *id,
+                    accounts_current_len: bytes.len(),
+                });
+
+            // NB: Empty files cannot be mapped.
+            bytes.extend(slack);
+            if bytes.is_empty() {
+                bytes.resize(8, 0);
+            }
+            std::fs::write(accounts.join(format!("{slot}.{id}")), bytes).unwrap();
+        }
+
+        let mut bank = DeserializableVersionedBank::default();
+        bank.slot = self.slot;
+        let accounts_db_fields = AccountsDbFields(
+            storages,
+            0,
+            self.slot,
+            BankHashInfo::default(),
+            Vec::new(),
+            Vec::new(),
+        );
+
+        let mut manifest = File::create(bank_dir.join(self.slot.to_string())).unwrap();
+        bincode::serialize_into(&mut manifest, &bank).unwrap();
+        bincode::serialize_into(&mut manifest, &accounts_db_fields).unwrap();
+        if self.trailing_fields {
+            // lamports_per_signature, incremental persistence, epoch accounts hash,
+            // versioned epoch stakes & accounts lattice hash.
+            bincode::serialize_into(&mut manifest, &0u64).unwrap();
+            bincode::serialize_into(&mut manifest, &self.incremental_persistence).unwrap();
+            bincode::serialize_into(&mut manifest, &None::<Hash>).unwrap();
+            bincode::serialize_into(&mut manifest, &HashMap::<u64, ()>::new()).unwrap();
+            bincode::serialize_into(&mut manifest, &self.accounts_lt_hash).unwrap();
+            if self.accounts_lt_hash {
+                // NB: 1024 `u16` elements.
+                manifest.write_all(&[0; 2048]).unwrap();
+            }
+        }
+        manifest.write_all(&self.trailing_bytes).unwrap();
+
+        root
+    }
+}
+
+/// Parses `args` as command line arguments.
+pub(crate) fn parse_args(args: &[&str]) -> Args {
+    Args::parse_from(std::iter::once("solana-snapshot-rpc").chain(args.iter().copied()))
+}
+
+/// Parses the arguments of the `rpc` subcommand.
+pub(crate) fn rpc_args(args: &[&str]) -> RpcArgs {
+    let args: Vec<_> = ["--source", "unused", "rpc"]
+        .into_iter()
+        .chain(args.iter().copied())
+        .collect();
+    match parse_args(&args).action {
+        Action::Rpc(rpc_args) => rpc_args,
+        _ => unreachable!(),
+    }
+}
+
+/// Opens the snapshot at `root` configured by the (source & index) `args`.
+pub(crate) fn open_snapshot(root: &Path, args: &[&str]) -> UnpackedSnapshotExtractor {
+    let args = source_args(root, args);
+
+    UnpackedSnapshotExtractor::open(
+        root,
+        &args.source,
+        Box::new(LoadProgressTracking { quiet: true, style: args.progress.manifest_style() }),
+    )
+}
+
+/// Opens the snapshot at `root` & builds its index.
+pub(crate) fn load_snapshot(root: &Path, args: &[&str]) -> HistoricalRpc {
+    let extractor = open_snapshot(root, args);
+
+    HistoricalRpc::load(
+        extractor,
+        &source_args(root, args).index,
+        &ProgressBar::hidden(),
+        &ProgressBar::hidden(),
+        &AtomicBool::default(),
+    )
+    .unwrap()
+}
+
+/// Parses the (source & index) `args` for the snapshot at `root`.
+pub(crate) fn source_args(root: &Path, args: &[&str]) -> Args {
+    let root = root.to_str().unwrap();
+    let args: Vec<_> = ["--source", root]
+        .into_iter()
+        .chain(args.iter().copied())
+        .chain(["checksum"])
+        .collect();
+
+    parse_args(&args)
+}
@@ -0,0 +1,71 @@
+use solana_account_decoder::parse_token::is_known_spl_token_id;
+use solana_sdk::pubkey::Pubkey;
+
+/// The Token-2022 program, the only token program whose accounts may carry
+/// extensions.
+const TOKEN_2022_PROGRAM_ID: Pubkey =
+    solana_sdk::pubkey!("TokenzQdBNbLqP5VEhdkAS6EPFLC1PYnBEvkLG4k4fG");
+/// Length of an SPL Token account (excluding Token-2022 extensions).
+const TOKEN_ACCOUNT_LEN: usize = 165;
+/// Length of an SPL Token multisig account. Token-2022 never sizes a token
+/// account with extensions to this length.
+const MULTISIG_LEN: usize = 355;
+/// Token-2022 `AccountType` discriminator (stored directly after the base
+/// account) identifying token accounts with extensions.
+const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
+
+/// Returns the `(mint, owner)` of an SPL Token (or Token-2022) account.
+pub(crate) fn parse_token_account(program: &Pubkey, data: &[u8]) -> Option<(Pubkey, Pubkey)> {
+    if !is_known_spl_token_id(program) {
+        return None;
+    }
+    match data.len() {
+        TOKEN_ACCOUNT_LEN => {}
+        // NB: A multisig's byte at the discriminator offset is arbitrary, so only
+        // accept the extension layout where it exists.
+        len if *program == TOKEN_2022_PROGRAM_ID
+            && len > TOKEN_ACCOUNT_LEN
+            && len != MULTISIG_LEN
+            && data[TOKEN_ACCOUNT_LEN] == ACCOUNT_TYPE_ACCOUNT => {}
+        _ => return None,
+    }
+
+    let mint = Pubkey::try_from(&data[0..32]).unwrap();
+    let owner = Pubkey::try_from(&data[32..64]).unwrap();
+
+    Some((mint, owner))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TOKEN_PROGRAM_ID: Pubkey =
+        solana_sdk::pubkey!("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+
+    fn token_account(len: usize, mint: Pubkey, owner: Pubkey) -> Vec<u8> {
+        let mut data = vec![0; len];
+        data[0..32].copy_from_slice(mint.as_ref());
+        data[32..64].copy_from_slice(owner.as_ref());
+        if len > TOKEN_ACCOUNT_LEN {
+            data[TOKEN_ACCOUNT_LEN] = ACCOUNT_TYPE_ACCOUNT;
+        }
+
+        data
+    }
+
+    #[test]
+    fn only_token_2022_accounts_carry_extensions() {
+        let (mint, owner) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let parse = |program, len| parse_token_account(program, &token_account(len, mint, owner));
+
+        for program in [&TOKEN_PROGRAM_ID, &TOKEN_2022_PROGRAM_ID] {
+            assert_eq!(parse(program, TOKEN_ACCOUNT_LEN), Some((mint, owner)));
+            // NB: Multisig accounts (with the discriminator byte set by chance).
+            assert_eq!(parse(program, MULTISIG_LEN), None);
+        }
+        assert_eq!(parse(&TOKEN_2022_PROGRAM_ID, 170), Some((mint, owner)));
+        assert_eq!(parse(&TOKEN_PROGRAM_ID, 170), None);
+        assert_eq!(parse(&Pubkey::new_unique(), TOKEN_ACCOUNT_LEN), None);
+    }
+}
@@ -1,28 +1,159 @@
-use std::fs::OpenOptions;
-use std::io::BufReader;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::fs::{Metadata, OpenOptions, ReadDir};
+use std::io::{BufRead, BufReader, ErrorKind, Read};
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
-use std::time::Instant;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
-use solana_runtime::snapshot_utils::SNAPSHOT_STATUS_CACHE_FILENAME;
-use tracing::info;
+use hashbrown::HashSet;
+use solana_runtime::snapshot_utils::{
+    SnapshotVersion, SNAPSHOT_STATUS_CACHE_FILENAME, SNAPSHOT_VERSION_FILENAME,
+};
+use solana_sdk::clock::Slot;
+use tracing::{debug, info, warn};
 
 use crate::append_vec::AppendVec;
+use crate::args::{OnError, OnParseError, SourceArgs};
+use crate::error::{SnapshotError, SnapshotResult};
+use crate::info::{AccountsHashKind, BankFields};
 use crate::solana::{
-    deserialize_from, AccountsDbFields, DeserializableVersionedBank,
+    deserialize_from, AccountsDbFields, DeserializableVersionedBank, ExtraFieldsToDeserialize,
     SerializableAccountStorageEntry,
 };
 use crate::utils::{parse_append_vec_name, ReadProgressTracking};
 
+/// Magic bytes of a zstd frame.
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+/// Delay before the first `--open-retries` retry, doubled for each subsequent
+/// retry.
+const OPEN_RETRY_BACKOFF: Duration = Duration::from_millis(100);
+/// Longest delay between `--open-retries` retries.
+const OPEN_RETRY_MAX_BACKOFF: Duration = Duration::from_secs(5);
+
 /// Extracts account data from snapshots that were unarchived to a file system.
+///
+/// The snapshot directory is only ever read (append vecs are mapped read-only),
+/// so it may live on a read-only mount.
 pub(crate) struct UnpackedSnapshotExtractor {
     root: PathBuf,
-    slot: u64,
+    /// Root of the incremental snapshot layered over `root` (if any).
+    incremental_root: Option<PathBuf>,
+    /// Slots whose append vecs are read from `incremental_root`.
+    incremental_slots: HashSet<Slot>,
+    follow_symlinks: bool,
+    on_file_error: OnError,
+    on_parse_error: OnParseError,
+    open_retries: u32,
+    /// Append vecs skipped due to [`OnError::Skip`].
+    skipped: Mutex<BTreeSet<(u64, u64)>>,
+    /// Append vecs with an account that failed to parse (skipped or truncated
+    /// per [`OnParseError`]).
+    corrupt: Mutex<BTreeSet<(u64, u64)>>,
+    /// Contents of the snapshot's version file (if present).
+    version: Option<String>,
+    bank: DeserializableVersionedBank,
     accounts_db_fields: AccountsDbFields<SerializableAccountStorageEntry>,
+    accounts_hash_kind: AccountsHashKind,
+    /// See [`UnpackedSnapshotExtractor::full_slot`].
+    full_slot: Option<Slot>,
+}
+
+/// The contents of a single snapshot's manifest.
+struct Manifest {
+    version: Option<String>,
+    bank: DeserializableVersionedBank,
+    accounts_db_fields: AccountsDbFields<SerializableAccountStorageEntry>,
+    accounts_hash_kind: AccountsHashKind,
+    /// Slot of the full snapshot an incremental snapshot was taken against (if
+    /// recorded in the manifest).
+    full_slot: Option<Slot>,
 }
 
 impl UnpackedSnapshotExtractor {
-    pub(crate) fn open(path: &Path, progress_tracking: Box<dyn ReadProgressTracking>) -> Self {
+    /// Opens the unpacked snapshot at `path` (configured by `args`), layering
+    /// `args.incremental_source` over it if set.
+    pub(crate) fn open(
+        path: &Path,
+        args: &SourceArgs,
+        progress_tracking: Box<dyn ReadProgressTracking>,
+    ) -> Self {
+        let mut manifest = Self::read_manifest(path, args, progress_tracking.as_ref());
+        let mut incremental_slots = HashSet::new();
+        if let Some(incremental_path) = &args.incremental_source {
+            let incremental =
+                Self::read_manifest(incremental_path, args, progress_tracking.as_ref());
+            assert!(
+                incremental.bank.slot > manifest.bank.slot,
+                "Incremental snapshot does not follow the full snapshot; full={}; incremental={}",
+                manifest.bank.slot,
+                incremental.bank.slot
+            );
+            match incremental.full_slot {
+                Some(full_slot) => assert_eq!(
+                    full_slot, manifest.bank.slot,
+                    "Incremental snapshot was taken against a different full snapshot; \
+                     expected={full_slot}; full={}",
+                    manifest.bank.slot
+                ),
+                None => warn!(
+                    "Incremental snapshot does not record its full snapshot slot, assuming it \
+                     matches --source"
+                ),
+            }
+            info!(
+                full_slot = manifest.bank.slot,
+                incremental_slot = incremental.bank.slot,
+                "Layering incremental snapshot"
+            );
+
+            // NB: The incremental manifest describes the newer bank, its storages
+            // override the full snapshot's for any overlapping slots.
+            incremental_slots = incremental.accounts_db_fields.0.keys().copied().collect();
+            let mut accounts_db_fields = incremental.accounts_db_fields;
+            for (slot, entries) in manifest.accounts_db_fields.0 {
+                accounts_db_fields.0.entry(slot).or_insert(entries);
+            }
+            manifest = Manifest {
+                version: incremental.version,
+                bank: incremental.bank,
+                accounts_db_fields,
+                accounts_hash_kind: incremental.accounts_hash_kind,
+                full_slot: incremental.full_slot,
+            };
+        }
+        if let Some(required) = args.require_slot {
+            assert_eq!(
+                manifest.bank.slot, required,
+                "Unexpected snapshot slot; expected={required}; actual={}",
+                manifest.bank.slot
+            );
+        }
+
+        UnpackedSnapshotExtractor {
+            root: path.to_path_buf(),
+            incremental_root: args.incremental_source.clone(),
+            incremental_slots,
+            follow_symlinks: args.follow_symlinks,
+            on_file_error: args.on_file_error,
+            on_parse_error: args.on_parse_error,
+            open_retries: args.open_retries,
+            skipped: Mutex::default(),
+            corrupt: Mutex::default(),
+            version: manifest.version,
+            bank: manifest.bank,
+            accounts_db_fields: manifest.accounts_db_fields,
+            accounts_hash_kind: manifest.accounts_hash_kind,
+            full_slot: manifest.full_slot,
+        }
+    }
+
+    fn read_manifest(
+        path: &Path,
+        args: &SourceArgs,
+        progress_tracking: &dyn ReadProgressTracking,
+    ) -> Manifest {
         let snapshots_dir = path.join("snapshots");
         let status_cache = snapshots_dir.join(SNAPSHOT_STATUS_CACHE_FILENAME);
         assert!(
@@ -30,6 +161,14 @@ impl UnpackedSnapshotExtractor {
             "Status cache is not a file; status_cache={status_cache:?}"
         );
 
+        // Validate the format before deserializing the manifest, as an incompatible
+        // layout would otherwise fail with an opaque bincode error.
+        let version = read_snapshot_version(path).unwrap_or_else(|err| panic!("{err}"));
+        match &version {
+            Some(version) => info!(version, "Snapshot version"),
+            None => warn!("Snapshot version file is missing, assuming a supported version"),
+        }
+
         let snapshot_files = snapshots_dir.read_dir().unwrap();
 
         let snapshot_file_path = snapshot_files
@@ -52,16 +191,44 @@ impl UnpackedSnapshotExtractor {
         );
         let mut snapshot_file = BufReader::new(snapshot_file);
 
+        // Some tooling zstd compresses the manifest, detect it by its magic bytes.
+        let mut snapshot_file: Box<dyn Read> =
+            if snapshot_file.fill_buf().unwrap().starts_with(&ZSTD_MAGIC) {
+                info!("Decompressing zstd snapshot manifest");
+                Box::new(zstd::stream::read::Decoder::with_buffer(snapshot_file).unwrap())
+            } else {
+                Box::new(snapshot_file)
+            };
+
         let pre_unpack = Instant::now();
         let versioned_bank: DeserializableVersionedBank =
             deserialize_from(&mut snapshot_file).unwrap();
-        let slot = versioned_bank.slot;
-        drop(versioned_bank);
         let versioned_bank_post_time = Instant::now();
 
         let accounts_db_fields: AccountsDbFields<SerializableAccountStorageEntry> =
             deserialize_from(&mut snapshot_file).unwrap();
         let accounts_db_fields_post_time = Instant::now();
+        let extra_fields = read_extra_fields(&mut snapshot_file).unwrap_or_else(|err| {
+            assert!(
+                !args.strict_manifest,
+                "Failed to parse trailing manifest fields; err={err}; \
+                 manifest={snapshot_file_path:?}"
+            );
+            warn!(%err, "Failed to parse trailing manifest fields");
+
+            None
+        });
+        if args.strict_manifest {
+            let trailing = std::io::copy(&mut snapshot_file, &mut std::io::sink()).unwrap();
+            assert_eq!(
+                trailing, 0,
+                "Manifest has trailing bytes; trailing={trailing}; manifest={snapshot_file_path:?}"
+            );
+        }
+        let accounts_hash_kind = accounts_hash_kind(extra_fields.as_ref());
+        let full_slot = extra_fields
+            .and_then(|extra| extra.incremental_snapshot_persistence)
+            .map(|persistence| persistence.full_slot);
         drop(snapshot_file);
 
         info!("Read bank fields in {:?}", versioned_bank_post_time - pre_unpack);
@@ -70,46 +237,510 @@ impl UnpackedSnapshotExtractor {
             accounts_db_fields_post_time - versioned_bank_post_time
         );
 
-        UnpackedSnapshotExtractor { root: path.to_path_buf(), slot, accounts_db_fields }
+        Manifest {
+            version,
+            bank: versioned_bank,
+            accounts_db_fields,
+            accounts_hash_kind,
+            full_slot,
+        }
     }
 
-    pub(crate) fn root(&self) -> &Path {
-        &self.root
+    pub(crate) const fn slot(&self) -> u64 {
+        self.bank.slot
     }
 
-    pub(crate) const fn slot(&self) -> u64 {
-        self.slot
+    pub(crate) fn version(&self) -> Option<&str> {
+        self.version.as_deref()
+    }
+
+    pub(crate) const fn bank(&self) -> &DeserializableVersionedBank {
+        &self.bank
+    }
+
+    pub(crate) fn bank_fields(&self) -> BankFields {
+        let bank = &self.bank;
+
+        BankFields {
+            slot: bank.slot,
+            epoch: bank.epoch,
+            block_height: bank.block_height,
+            capitalization: bank.capitalization,
+            hash: bank.hash,
+            parent_hash: bank.parent_hash,
+            genesis_creation_time: bank.genesis_creation_time,
+            inflation: bank.inflation,
+            epoch_schedule: bank.epoch_schedule,
+            fee_rate_governor: bank.fee_rate_governor.clone(),
+            rent: bank.rent_collector.rent,
+        }
+    }
+
+    pub(crate) const fn accounts_db_fields(
+        &self,
+    ) -> &AccountsDbFields<SerializableAccountStorageEntry> {
+        &self.accounts_db_fields
+    }
+
+    pub(crate) const fn accounts_hash_kind(&self) -> AccountsHashKind {
+        self.accounts_hash_kind
+    }
+
+    /// Slot of the full snapshot the (layered) incremental snapshot was taken
+    /// against, `None` for full snapshots & incremental snapshots that do not
+    /// record it.
+    pub(crate) const fn full_slot(&self) -> Option<Slot> {
+        self.full_slot
+    }
+
+    /// Whether the snapshot is an incremental snapshot (layered over
+    /// `--source` or recording the full snapshot it was taken against).
+    pub(crate) const fn is_incremental(&self) -> bool {
+        self.incremental_root.is_some() || self.full_slot.is_some()
     }
 
+    pub(crate) fn append_vec_path(&self, slot: u64, id: u64) -> PathBuf {
+        self.slot_root(slot).join(format!("accounts/{slot}.{id}"))
+    }
+
+    /// The snapshot root holding the append vecs of `slot`.
+    fn slot_root(&self, slot: u64) -> &Path {
+        match &self.incremental_root {
+            Some(incremental_root) if self.incremental_slots.contains(&slot) => incremental_root,
+            _ => &self.root,
+        }
+    }
+
+    /// Confirms every append vec referenced by the manifest exists & is at
+    /// least `accounts_current_len` bytes long.
+    pub(crate) fn verify_append_vec_files(&self) -> Vec<AppendVecFileError> {
+        let mut errors = Vec::new();
+        for (slot, entries) in &self.accounts_db_fields.0 {
+            for entry in entries {
+                let id = entry.id as u64;
+                let path = self.append_vec_path(*slot, id);
+
+                let Ok(metadata) = std::fs::metadata(&path) else {
+                    errors.push(AppendVecFileError::Missing { slot: *slot, id });
+                    continue;
+                };
+                if metadata.len() < entry.accounts_current_len as u64 {
+                    errors.push(AppendVecFileError::TooShort {
+                        slot: *slot,
+                        id,
+                        len: metadata.len(),
+                        expected: entry.accounts_current_len,
+                    });
+                }
+            }
+        }
+
+        errors
+    }
+
+    /// Yields every append vec, handling those that fail to open according to
+    /// the configured [`OnError`] policy.
     pub(crate) fn unboxed_iter(&self) -> impl Iterator<Item = AppendVec> + '_ {
         self.iter_streams()
+            .filter_map(|append_vec| match append_vec {
+                Ok(append_vec) => Some(append_vec),
+                Err(err) => match self.on_file_error {
+                    OnError::Abort => panic!("{err}"),
+                    OnError::Skip => {
+                        warn!(%err, "Skipping append vec");
+                        if let Some(append_vec) = err.append_vec() {
+                            self.skipped.lock().unwrap().insert(append_vec);
+                        }
+
+                        None
+                    }
+                },
+            })
     }
 
-    fn iter_streams(&self) -> impl Iterator<Item = AppendVec> + '_ {
-        let accounts_dir = self.root.join("accounts");
-        accounts_dir.read_dir().unwrap().map(move |file| {
-            let file = file.unwrap();
-            let name = file.file_name();
+    /// Append vecs that were skipped by scans so far.
+    pub(crate) fn skipped(&self) -> Vec<(u64, u64)> {
+        self.skipped.lock().unwrap().iter().copied().collect()
+    }
 
-            let (slot, version) = parse_append_vec_name(&name);
+    pub(crate) const fn on_parse_error(&self) -> OnParseError {
+        self.on_parse_error
+    }
 
-            self.open_append_vec(slot, version, &accounts_dir.join(&name))
+    /// Records an append vec with an account that failed to parse.
+    pub(crate) fn mark_corrupt(&self, slot: u64, id: u64) {
+        self.corrupt.lock().unwrap().insert((slot, id));
+    }
+
+    /// Append vecs with an account that failed to parse in scans so far.
+    pub(crate) fn corrupt(&self) -> Vec<(u64, u64)> {
+        self.corrupt.lock().unwrap().iter().copied().collect()
+    }
+
+    /// Logs the append vecs skipped (file errors) & found corrupt (parse
+    /// errors) by scans so far.
+    pub(crate) fn log_scan_errors(&self) {
+        let skipped = self.skipped();
+        if !skipped.is_empty() {
+            warn!(count = skipped.len(), ?skipped, "Skipped unreadable append vecs");
+        }
+        let corrupt = self.corrupt();
+        if !corrupt.is_empty() {
+            warn!(
+                count = corrupt.len(),
+                ?corrupt,
+                policy = ?self.on_parse_error,
+                "Found corrupt append vecs"
+            );
+        }
+    }
+
+    /// Yields the append vecs (of both the full & incremental snapshot, if
+    /// layered) ordered by `(slot, id)`.
+    fn iter_streams(&self) -> impl Iterator<Item = SnapshotResult<AppendVec>> + '_ {
+        let mut append_vecs: Vec<_> = std::iter::once(self.root.as_path())
+            .chain(self.incremental_root.as_deref())
+            .flat_map(|root| {
+                let accounts_dir = root.join("accounts");
+                match accounts_dir.read_dir() {
+                    Ok(entries) => self
+                        .list_append_vecs(root, entries)
+                        .into_iter()
+                        .map(Ok)
+                        .collect(),
+                    Err(err) => {
+                        vec![Err(SnapshotError::AccountsDirRead { path: accounts_dir, err })]
+                    }
+                }
+            })
+            .collect();
+        // NB: Errors sort first, so unreadable accounts directories are reported up
+        // front.
+        append_vecs
+            .sort_by_key(|append_vec| append_vec.as_ref().ok().map(|(slot, id, _)| (*slot, *id)));
+
+        append_vecs.into_iter().map(move |append_vec| {
+            let (slot, id, path) = append_vec?;
+
+            self.open_append_vec(slot, id, &path)
         })
     }
 
-    pub(crate) fn open_append_vec(&self, slot: u64, id: u64, path: &Path) -> AppendVec {
-        let known_vecs = self
-            .accounts_db_fields
+    /// Lists the append vecs in the accounts directory of `root` ordered by
+    /// `(slot, id)`, skipping (with a warning) entries that cannot be read, are
+    /// not named `<slot>.<id>` or are not referenced by the manifest.
+    fn list_append_vecs(&self, root: &Path, entries: ReadDir) -> Vec<(u64, u64, PathBuf)> {
+        // Files that were already yielded, used to skip hardlinks to the same file.
+        let mut file_ids = HashSet::new();
+        let mut hardlinks = 0;
+        let mut append_vecs: Vec<_> = entries
+            .filter_map(|entry| {
+                let (path, file_type) =
+                    match entry.and_then(|entry| Ok((entry.path(), entry.file_type()?))) {
+                        Ok(entry) => entry,
+                        Err(err) => {
+                            warn!(%err, "Skipping unreadable accounts directory entry");
+
+                            return None;
+                        }
+                    };
+                if file_type.is_symlink() && !self.follow_symlinks {
+                    warn!(?path, "Skipping symlinked append vec");
+
+                    return None;
+                }
+                let metadata = match std::fs::metadata(&path) {
+                    Ok(metadata) => metadata,
+                    Err(err) => {
+                        warn!(?path, %err, "Skipping unreadable append vec");
+
+                        return None;
+                    }
+                };
+                if let Some(file_id) = file_id(&metadata) {
+                    if !file_ids.insert(file_id) {
+                        hardlinks += 1;
+
+                        return None;
+                    }
+                }
+
+                let Some((slot, id)) = path.file_name().and_then(parse_append_vec_name) else {
+                    warn!(?path, "Skipping unrecognized file in accounts directory");
+
+                    return None;
+                };
+                if self.known_append_vec(slot, id).is_none() {
+                    warn!(?path, "Skipping append vec not referenced by the manifest");
+
+                    return None;
+                }
+                if self.slot_root(slot) != root {
+                    debug!(?path, "Skipping append vec overridden by the incremental snapshot");
+
+                    return None;
+                }
+
+                Some((slot, id, path))
+            })
+            .collect();
+        append_vecs.sort_unstable_by_key(|(slot, id, _)| (*slot, *id));
+        if hardlinks > 0 {
+            info!(hardlinks, "Skipped hardlinked duplicate append vecs");
+        }
+
+        append_vecs
+    }
+
+    fn known_append_vec(&self, slot: u64, id: u64) -> Option<&SerializableAccountStorageEntry> {
+        self.accounts_db_fields
             .0
-            .get(&slot)
-            .map(|v| &v[..])
-            .unwrap_or(&[]);
-        let known_vec = known_vecs.iter().find(|entry| entry.id == (id as usize));
-        let known_vec = match known_vec {
-            None => panic!("Unknown vec"),
-            Some(v) => v,
+            .get(&slot)?
+            .iter()
+            .find(|entry| entry.id == (id as usize))
+    }
+
+    pub(crate) fn open_append_vec(
+        &self,
+        slot: u64,
+        id: u64,
+        path: &Path,
+    ) -> SnapshotResult<AppendVec> {
+        let known_vec = self
+            .known_append_vec(slot, id)
+            .ok_or(SnapshotError::UnknownAppendVec { slot, id })?;
+
+        // NB: A validator may still be writing the snapshot directory, so missing or
+        // short files are retried (if configured).
+        let mut attempt = 0;
+        loop {
+            match AppendVec::new_from_file(path, known_vec.accounts_current_len, slot, id) {
+                Err(err)
+                    if attempt < self.open_retries
+                        && matches!(err.kind(), ErrorKind::NotFound | ErrorKind::UnexpectedEof) =>
+                {
+                    let backoff = (OPEN_RETRY_BACKOFF * 2u32.saturating_pow(attempt))
+                        .min(OPEN_RETRY_MAX_BACKOFF);
+                    warn!(slot, id, %err, attempt, ?backoff, "Failed to open append vec, retrying");
+                    std::thread::sleep(backoff);
+                    attempt += 1;
+                }
+                result => {
+                    return result.map_err(|err| SnapshotError::AppendVecOpen { slot, id, err })
+                }
+            }
+        }
+    }
+}
+
+/// Reads the snapshot's version file, returning `None` if it does not exist &
+/// an error if it names an unsupported version.
+fn read_snapshot_version(root: &Path) -> SnapshotResult<Option<String>> {
+    let path = root.join(SNAPSHOT_VERSION_FILENAME);
+    let version = match std::fs::read_to_string(&path) {
+        Ok(version) => version.trim().to_string(),
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(SnapshotError::VersionRead { path, err }),
+    };
+    SnapshotVersion::from_str(&version)
+        .map_err(|_| SnapshotError::UnsupportedVersion { version: version.clone() })?;
+
+    Ok(Some(version))
+}
+
+/// Reads the optional manifest fields following the accounts DB fields,
+/// returning `None` if the manifest ends after the accounts DB fields (as older
+/// manifests do).
+fn read_extra_fields(reader: &mut impl Read) -> bincode::Result<Option<ExtraFieldsToDeserialize>> {
+    let mut lamports_per_signature = [0; 8];
+    match reader.read_exact(&mut lamports_per_signature) {
+        Ok(()) => {}
+        Err(err) if err.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(err) => return Err(err.into()),
+    }
+
+    deserialize_from(reader).map(Some)
+}
+
+/// Detects the accounts hash kind from the optional manifest fields following
+/// the accounts DB fields.
+fn accounts_hash_kind(extra_fields: Option<&ExtraFieldsToDeserialize>) -> AccountsHashKind {
+    match extra_fields {
+        None => AccountsHashKind::Unknown,
+        Some(extra) if extra.accounts_lt_hash.is_some() => AccountsHashKind::Lattice,
+        Some(extra) if extra.incremental_snapshot_persistence.is_some() => {
+            AccountsHashKind::IncrementalMerkle
+        }
+        Some(_) => AccountsHashKind::Merkle,
+    }
+}
+
+/// Uniquely identifies a file (regardless of how many links point to it).
+#[cfg(unix)]
+fn file_id(metadata: &Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_: &Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+#[derive(Debug)]
+pub(crate) enum AppendVecFileError {
+    Missing { slot: u64, id: u64 },
+    TooShort { slot: u64, id: u64, len: u64, expected: usize },
+}
+
+impl Display for AppendVecFileError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AppendVecFileError::Missing { slot, id } => {
+                write!(f, "Missing append vec; slot={slot}; id={id}")
+            }
+            AppendVecFileError::TooShort { slot, id, len, expected } => write!(
+                f,
+                "Append vec too short; slot={slot}; id={id}; len={len}; expected={expected}"
+            ),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::test_utils::{load_snapshot, open_snapshot, SnapshotBuilder, TestAccount};
+
+    #[test]
+    fn accounts_hash_kind_from_trailing_fields() {
+        let cases = [
+            (SnapshotBuilder::new(10), AccountsHashKind::Merkle),
+            (SnapshotBuilder::new(10).incremental(5), AccountsHashKind::IncrementalMerkle),
+            (SnapshotBuilder::new(10).accounts_lt_hash(), AccountsHashKind::Lattice),
+            (SnapshotBuilder::new(10).without_trailing_fields(), AccountsHashKind::Unknown),
+        ];
+        for (builder, expected) in cases {
+            let snapshot = builder.write();
+
+            for args in [&[][..], &["--strict-manifest"]] {
+                assert_eq!(open_snapshot(snapshot.path(), args).accounts_hash_kind(), expected);
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Manifest has trailing bytes")]
+    fn strict_manifest_rejects_unknown_trailing_bytes() {
+        let snapshot = SnapshotBuilder::new(10)
+            .trailing_bytes(vec![1, 2, 3])
+            .write();
+        assert_eq!(
+            open_snapshot(snapshot.path(), &[]).accounts_hash_kind(),
+            AccountsHashKind::Merkle
+        );
+
+        open_snapshot(snapshot.path(), &["--strict-manifest"]);
+    }
+
+    #[test]
+    fn snapshot_version_file() {
+        let read = |version| {
+            read_snapshot_version(SnapshotBuilder::new(10).version(version).write().path())
         };
 
-        AppendVec::new_from_file(path, known_vec.accounts_current_len, slot, id).unwrap()
+        assert_eq!(read(Some("1.2.0")).unwrap(), Some("1.2.0".to_string()));
+        assert_eq!(read(None).unwrap(), None);
+        assert!(matches!(
+            read(Some("9.9.9")),
+            Err(SnapshotError::UnsupportedVersion { version }) if version == "9.9.9"
+        ));
+    }
+
+    #[test]
+    #[should_panic(expected = "Unsupported snapshot version")]
+    fn rejects_unsupported_snapshot_version() {
+        let snapshot = SnapshotBuilder::new(10).version(Some("9.9.9")).write();
+
+        open_snapshot(snapshot.path(), &[]);
+    }
+
+    #[test]
+    fn layers_incremental_snapshot() {
+        let owner = Pubkey::new_unique();
+        let [updated, unchanged, created] =
+            [Pubkey::new_unique(), Pubkey::new_unique(), Pubkey::new_unique()];
+        let full = SnapshotBuilder::new(10)
+            .append_vec(
+                10,
+                0,
+                vec![
+                    TestAccount::new(updated, 1, owner, vec![]),
+                    TestAccount::new(unchanged, 2, owner, vec![]),
+                ],
+            )
+            .write();
+
+        // NB: Older incremental snapshots do not record their full snapshot slot.
+        for builder in [SnapshotBuilder::new(12).incremental(10), SnapshotBuilder::new(12)] {
+            let incremental = builder
+                .append_vec(
+                    12,
+                    0,
+                    vec![
+                        TestAccount::new(updated, 5, owner, vec![]),
+                        TestAccount::new(created, 6, owner, vec![]),
+                    ],
+                )
+                .write();
+            let args = ["--incremental-source", incremental.path().to_str().unwrap()];
+
+            assert_eq!(open_snapshot(full.path(), &args).slot(), 12);
+            let rpc = load_snapshot(full.path(), &args);
+            assert_eq!(rpc.len(), 3);
+            assert_eq!(rpc.get_account(&updated).unwrap().lamports, 5);
+            assert_eq!(rpc.get_account(&unchanged).unwrap().lamports, 2);
+            assert_eq!(rpc.get_account(&created).unwrap().lamports, 6);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Incremental snapshot was taken against a different full snapshot")]
+    fn rejects_incremental_of_other_full_snapshot() {
+        let full = SnapshotBuilder::new(10).write();
+        let incremental = SnapshotBuilder::new(12).incremental(9).write();
+
+        open_snapshot(full.path(), &["--incremental-source", incremental.path().to_str().unwrap()]);
+    }
+
+    #[test]
+    fn skips_junk_in_accounts_dir() {
+        let key = Pubkey::new_unique();
+        let snapshot = SnapshotBuilder::new(10)
+            .append_vec(10, 0, vec![TestAccount::new(key, 1, Pubkey::new_unique(), vec![])])
+            .write();
+        let accounts = snapshot.path().join("accounts");
+        std::fs::write(accounts.join("junk.txt"), b"junk").unwrap();
+        std::fs::write(accounts.join("10.x"), b"junk").unwrap();
+        std::fs::write(accounts.join("99.0"), b"junk").unwrap();
+        std::fs::create_dir(accounts.join("10.1")).unwrap();
+
+        let extractor = open_snapshot(snapshot.path(), &[]);
+        let append_vecs: Vec<_> = extractor
+            .iter_streams()
+            .map(|append_vec| {
+                let append_vec = append_vec.unwrap();
+
+                (append_vec.slot(), append_vec.id())
+            })
+            .collect();
+        assert_eq!(append_vecs, [(10, 0)]);
+
+        let rpc = load_snapshot(snapshot.path(), &[]);
+        assert_eq!(rpc.len(), 1);
+        assert!(rpc.get_account(&key).is_some());
     }
 }
@@ -1,45 +1,158 @@
 use std::ffi::OsStr;
 use std::io::{IoSliceMut, Read};
+use std::ops::Deref;
 use std::path::Path;
+use std::rc::Rc;
+use std::time::Duration;
 
-use indicatif::{ProgressBar, ProgressBarIter, ProgressStyle};
+use indicatif::{ProgressBar, ProgressBarIter, ProgressDrawTarget, ProgressStyle};
+use solana_sdk::clock::Slot;
+use tracing::{debug, info, warn};
 
 use crate::append_vec::{AppendVec, StoredAccountMeta};
+use crate::args::OnParseError;
+use crate::error::AppendVecError;
+use crate::unpacked::UnpackedSnapshotExtractor;
 
-pub(crate) fn parse_append_vec_name(name: &OsStr) -> (u64, u64) {
-    let name = name.to_str().unwrap();
-    let mut parts = name.splitn(2, '.');
-    let slot = parts.next().unwrap().parse().unwrap();
-    let id = parts.next().unwrap().parse().unwrap();
+/// Default `--progress-template`.
+pub(crate) const DEFAULT_PROGRESS_TEMPLATE: &str = "{prefix:>15.bold.dim} {spinner:.green} \
+                                                    rate={per_sec} processed={human_pos} \
+                                                    {elapsed_precise:.cyan}";
+/// Default `--manifest-progress-template`.
+pub(crate) const DEFAULT_MANIFEST_PROGRESS_TEMPLATE: &str =
+    "{prefix:>15.bold.dim} {spinner:.green} [{bar:.cyan/blue}] {bytes}/{total_bytes} ({percent}%)";
 
-    (slot, id)
+/// Parses an append vec file name (`<slot>.<id>`), returning `None` if the
+/// name is not of that form.
+pub(crate) fn parse_append_vec_name(name: &OsStr) -> Option<(u64, u64)> {
+    let (slot, id) = name.to_str()?.split_once('.')?;
+
+    Some((slot.parse().ok()?, id.parse().ok()?))
 }
 
+/// Yields every account stored in `append_vec`.
+///
+/// Iteration stops at `current_len` (the manifest's account bytes), never
+/// reading the slack space between `current_len` & the end of the file.
 pub(crate) fn append_vec_iter(
     append_vec: &AppendVec,
-) -> impl Iterator<Item = StoredAccountMetaHandle> {
+) -> impl Iterator<Item = StoredAccountMetaHandle<&AppendVec>> {
+    let mut offset = 0usize;
+    std::iter::from_fn(move || {
+        if offset >= append_vec.len() {
+            return None;
+        }
+
+        let (_, next_offset) = append_vec.get_account(offset)?;
+        let account = StoredAccountMetaHandle::new(append_vec, offset);
+        offset = next_offset;
+
+        Some(account)
+    })
+}
+
+/// Yields every account in the snapshot alongside the `(slot, id)` of the
+/// append vec storing it.
+pub(crate) fn accounts_with_location(
+    extractor: &UnpackedSnapshotExtractor,
+) -> impl Iterator<Item = (Slot, u64, StoredAccountMetaHandle<Rc<AppendVec>>)> + '_ {
+    extractor.unboxed_iter().flat_map(|append_vec| {
+        let slot = append_vec.slot();
+        let id = append_vec.id();
+
+        scan_append_vec(extractor, Rc::new(append_vec)).map(move |account| (slot, id, account))
+    })
+}
+
+/// Yields every account stored in `append_vec`, handling accounts that fail
+/// to parse according to the extractor's [`OnParseError`] policy.
+pub(crate) fn scan_append_vec<'a, V>(
+    extractor: &'a UnpackedSnapshotExtractor,
+    append_vec: V,
+) -> impl Iterator<Item = StoredAccountMetaHandle<V>> + 'a
+where
+    V: Deref<Target = AppendVec> + Clone + 'a,
+{
+    let slot = append_vec.slot();
+    let id = append_vec.id();
+    debug!(
+        target: "snapshot_etl::scan",
+        slot,
+        id,
+        len = append_vec.len(),
+        "Scanning append vec"
+    );
+
+    // NB: Skipping requires knowing the append vec is corrupt before yielding any
+    // of its accounts.
+    let mut done = false;
+    if extractor.on_parse_error() == OnParseError::Skip {
+        if let Err(err) = validate_append_vec(&append_vec) {
+            warn!(
+                target: "snapshot_etl::parse",
+                slot,
+                id,
+                %err,
+                "Corrupt append vec, skipping it"
+            );
+            extractor.mark_corrupt(slot, id);
+            done = true;
+        }
+    }
+
     let mut offset = 0usize;
-    std::iter::repeat_with(move || {
-        append_vec.get_account(offset).map(|(_, next_offset)| {
-            let account = StoredAccountMetaHandle::new(append_vec, offset);
-            offset = next_offset;
-            account
-        })
+    std::iter::from_fn(move || {
+        if done {
+            return None;
+        }
+
+        let (_, next_offset) = match append_vec.try_get_account(offset) {
+            Ok(account) => account?,
+            Err(err) => {
+                assert!(
+                    extractor.on_parse_error() != OnParseError::Abort,
+                    "Corrupt append vec; slot={slot}; id={id}; err={err}"
+                );
+                warn!(
+                    target: "snapshot_etl::parse",
+                    slot,
+                    id,
+                    %err,
+                    "Corrupt append vec, skipping its remaining accounts"
+                );
+                extractor.mark_corrupt(slot, id);
+                done = true;
+
+                return None;
+            }
+        };
+        let account = StoredAccountMetaHandle::new(append_vec.clone(), offset);
+        offset = next_offset;
+
+        Some(account)
     })
-    .take_while(|account| account.is_some())
-    .flatten()
 }
 
-pub(crate) struct StoredAccountMetaHandle<'a> {
-    append_vec: &'a AppendVec,
+/// Parses every account header in `append_vec`, returning the first error.
+fn validate_append_vec(append_vec: &AppendVec) -> Result<(), AppendVecError> {
+    let mut offset = 0;
+    while let Some((_, next_offset)) = append_vec.try_get_account(offset)? {
+        offset = next_offset;
+    }
+
+    Ok(())
+}
+
+pub(crate) struct StoredAccountMetaHandle<V> {
+    append_vec: V,
     offset: usize,
 }
 
-impl<'a> StoredAccountMetaHandle<'a> {
-    pub(crate) const fn new(
-        append_vec: &'a AppendVec,
-        offset: usize,
-    ) -> StoredAccountMetaHandle<'a> {
+impl<V> StoredAccountMetaHandle<V>
+where
+    V: Deref<Target = AppendVec>,
+{
+    pub(crate) const fn new(append_vec: V, offset: usize) -> StoredAccountMetaHandle<V> {
         Self { append_vec, offset }
     }
 
@@ -48,6 +161,31 @@ impl<'a> StoredAccountMetaHandle<'a> {
     }
 }
 
+/// Logs the progress of the index build every `interval` until `accounts_bar`
+/// is finished. Used in place of the progress bars when running quietly.
+pub(crate) fn spawn_progress_logger(
+    accounts_bar: ProgressBar,
+    unique_accounts_bar: ProgressBar,
+    interval: Duration,
+) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(interval);
+        if accounts_bar.is_finished() {
+            break;
+        }
+
+        let accounts = accounts_bar.position();
+        let elapsed = accounts_bar.elapsed();
+        info!(
+            accounts,
+            unique_accounts = unique_accounts_bar.position(),
+            rate = (accounts as f64 / elapsed.as_secs_f64()) as u64,
+            ?elapsed,
+            "Index progress"
+        );
+    });
+}
+
 pub(crate) trait ReadProgressTracking {
     fn new_read_progress_tracker(
         &self,
@@ -57,7 +195,10 @@ pub(crate) trait ReadProgressTracking {
     ) -> Box<dyn Read>;
 }
 
-pub(crate) struct LoadProgressTracking {}
+pub(crate) struct LoadProgressTracking {
+    pub(crate) quiet: bool,
+    pub(crate) style: ProgressStyle,
+}
 
 impl ReadProgressTracking for LoadProgressTracking {
     fn new_read_progress_tracker(
@@ -66,15 +207,11 @@ impl ReadProgressTracking for LoadProgressTracking {
         rd: Box<dyn Read>,
         file_len: u64,
     ) -> Box<dyn Read> {
-        let progress_bar = ProgressBar::new(file_len).with_style(
-            ProgressStyle::with_template(
-                "{prefix:>15.bold.dim} {spinner:.green} [{bar:.cyan/blue}] {bytes}/{total_bytes} \
-                 ({percent}%)",
-            )
-            .unwrap()
-            .progress_chars("#>-"),
-        );
+        let progress_bar = ProgressBar::new(file_len).with_style(self.style.clone());
         progress_bar.set_prefix("manifest");
+        if self.quiet {
+            progress_bar.set_draw_target(ProgressDrawTarget::hidden());
+        }
 
         Box::new(LoadProgressTracker { rd: progress_bar.wrap_read(rd), progress_bar })
     }
@@ -108,3 +245,37 @@ impl Read for LoadProgressTracker {
         self.rd.read_exact(buf)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::io::Write;
+
+    use solana_sdk::pubkey::Pubkey;
+
+    use super::*;
+    use crate::test_utils::{append_vec_bytes, TestAccount};
+
+    #[test]
+    fn append_vec_iter_stops_at_current_len() {
+        let key = Pubkey::new_unique();
+        let stored = append_vec_bytes(&[TestAccount::new(key, 1, Pubkey::new_unique(), vec![1])]);
+        // NB: The slack holds a parseable account, which must not be yielded.
+        let slack = append_vec_bytes(&[TestAccount::new(
+            Pubkey::new_unique(),
+            2,
+            Pubkey::new_unique(),
+            vec![],
+        )]);
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        file.write_all(&stored).unwrap();
+        file.write_all(&slack).unwrap();
+        file.write_all(&[0xff; 13]).unwrap();
+
+        let append_vec = AppendVec::new_from_file(file.path(), stored.len(), 0, 0).unwrap();
+        let keys: Vec<_> = append_vec_iter(&append_vec)
+            .map(|account| account.access().unwrap().meta.pubkey)
+            .collect();
+
+        assert_eq!(keys, [key]);
+    }
+}